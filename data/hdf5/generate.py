@@ -0,0 +1,59 @@
+#!/usr/bin/env python3
+"""Write HDF5 files with h5py to test the reader against files from the HDF5 library.
+
+Run from the root of the repository:
+
+    python3 data/hdf5/generate.py
+    cargo test hdf5 -- --ignored
+
+The expected contents are repeated in the ignored tests in src/utils/hdf5/mod.rs, so keep both in
+sync.
+"""
+
+import os
+
+import h5py
+import numpy as np
+
+DIR = os.path.dirname(os.path.abspath(__file__))
+
+
+def mldata():
+    """Layout of the files from mldata.org: version 0 superblock and old style groups."""
+    with h5py.File(os.path.join(DIR, 'mldata.hdf5'), 'w', libver='earliest') as f:
+        data = f.create_group('data')
+        data['data'] = np.arange(20, dtype=np.uint8).reshape(4, 5)
+        data['label'] = np.array([[0.0, 1.0, 2.0, 1.0, 0.0]])
+        data['double0'] = np.array([[1.5, -2.0, 3.25], [0.0, 1e10, -7.5]])
+        data['int1'] = np.array([1, -2, 3, -4], dtype=np.int32)
+        descr = f.create_group('data_descr')
+        descr['ordering'] = np.array([b'data', b'label'])
+        f.attrs['name'] = b'test'
+
+
+def chunked():
+    """Chunked datasets with filters and partial chunks at the edges."""
+    values = np.arange(35 * 11, dtype=np.float64).reshape(35, 11) / 4
+    with h5py.File(os.path.join(DIR, 'chunked.hdf5'), 'w', libver='earliest') as f:
+        f.create_dataset('plain', data=values, chunks=(8, 4))
+        f.create_dataset('deflate', data=values, chunks=(8, 4), compression='gzip', compression_opts=6)
+        f.create_dataset('shuffle', data=values, chunks=(8, 4), compression='gzip', shuffle=True)
+        f.create_dataset('fletcher32', data=values, chunks=(8, 4), fletcher32=True)
+        f.create_dataset('sparse', shape=(35, 11), dtype=np.float64, chunks=(8, 4))[0, 0] = 1
+
+
+def latest():
+    """Version 3 superblock, version 2 object headers and link messages."""
+    with h5py.File(os.path.join(DIR, 'latest.hdf5'), 'w', libver='latest') as f:
+        group = f.create_group('group')
+        group['values'] = np.array([1, 2, 3], dtype=np.uint64)
+        group['strings'] = np.array([b'a', b'bc', b'def'])
+        group.attrs['description'] = 'variable length string'
+        group.attrs['scale'] = np.float32(0.5)
+        f['link'] = h5py.SoftLink('/group/values')
+
+
+if __name__ == '__main__':
+    mldata()
+    chunked()
+    latest()
@@ -0,0 +1,194 @@
+//! Mini-batch iteration
+//!
+//! Mini-batches are built lazily from the native storage of a data set. Only one batch at a time
+//! is converted into canonical representation.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use mldata::batch::MiniBatches;
+//! use mldata::mldata_mnist_original::DataSet;
+//!
+//! let data = DataSet::new().create().unwrap().load_data().unwrap();
+//!
+//! for epoch in 0..10 {
+//!     for (x, y) in MiniBatches::new(128).shuffle(epoch).drop_last(true).iter(&data) {
+//!         assert_eq!(x.shape(), [128, 784]);
+//!         assert_eq!(y.shape(), [128, 1]);
+//!     }
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use ndarray::Array2;
+
+use canonical::CanonicalSamples;
+use utils::random::Rng;
+
+/// Configure mini-batch iteration.
+///
+/// This structure implements the builder pattern. Call [`iter`](#method.iter) to iterate over
+/// the mini-batches of a data set.
+#[derive(Debug, Clone)]
+pub struct MiniBatches {
+    batch_size: usize,
+    seed: Option<u64>,
+    drop_last: bool,
+}
+
+impl MiniBatches {
+    /// Iterate over batches of `batch_size` samples in the original order.
+    pub fn new(batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batch size must not be zero");
+        MiniBatches {
+            batch_size,
+            seed: None,
+            drop_last: false,
+        }
+    }
+
+    pub fn batch_size(&mut self, n: usize) -> &mut Self {
+        assert!(n > 0, "batch size must not be zero");
+        self.batch_size = n;
+        self
+    }
+
+    /// Shuffle the samples before splitting them into batches.
+    pub fn shuffle(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Skip the last batch if it contains less than `batch_size` samples.
+    pub fn drop_last(&mut self, b: bool) -> &mut Self {
+        self.drop_last = b;
+        self
+    }
+
+    pub fn iter<'a, D: CanonicalSamples + ?Sized>(&self, data: &'a D) -> BatchIter<'a, D> {
+        self.iter_as(data)
+    }
+
+    /// Like [`iter`](#method.iter), but with element type `T` instead of `f64`.
+    pub fn iter_as<'a, T, D>(&self, data: &'a D) -> BatchIter<'a, D, T>
+        where D: CanonicalSamples<T> + ?Sized
+    {
+        let (n_samples, _, _) = data.canonical_shape();
+        let mut order: Vec<_> = (0..n_samples).collect();
+        if let Some(seed) = self.seed {
+            Rng::new(seed).shuffle(&mut order);
+        }
+        if self.drop_last {
+            order.truncate(n_samples - n_samples % self.batch_size);
+        }
+
+        BatchIter {
+            data,
+            order,
+            batch_size: self.batch_size,
+            pos: 0,
+            _elem: PhantomData,
+        }
+    }
+}
+
+/// Iterator over mini-batches in canonical representation.
+///
+/// Created by [`MiniBatches::iter`](struct.MiniBatches.html#method.iter) and
+/// [`MiniBatches::iter_as`](struct.MiniBatches.html#method.iter_as).
+pub struct BatchIter<'a, D: 'a + ?Sized, T = f64> {
+    data: &'a D,
+    order: Vec<usize>,
+    batch_size: usize,
+    pos: usize,
+    _elem: PhantomData<T>,
+}
+
+impl<'a, D, T> Iterator for BatchIter<'a, D, T>
+    where D: CanonicalSamples<T> + ?Sized,
+          T: Clone + Default,
+{
+    type Item = (Array2<T>, Array2<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.order.len() {
+            return None
+        }
+
+        let end = (self.pos + self.batch_size).min(self.order.len());
+        let batch = self.data.select_canonical(&self.order[self.pos..end]);
+        self.pos = end;
+        Some(batch)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.order.len() - self.pos;
+        let n = (remaining + self.batch_size - 1) / self.batch_size;
+        (n, Some(n))
+    }
+}
+
+impl<'a, D, T> ExactSizeIterator for BatchIter<'a, D, T>
+    where D: CanonicalSamples<T> + ?Sized,
+          T: Clone + Default,
+{}
+
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr2, ArrayViewMut1};
+    use canonical::Real;
+    use super::*;
+
+    /// Sample i has features [i, 2i] and target [i % 2].
+    struct Counting(usize);
+
+    impl<T: Real> CanonicalSamples<T> for Counting {
+        fn canonical_shape(&self) -> (usize, usize, usize) {
+            (self.0, 2, 1)
+        }
+
+        fn write_canonical(&self, idx: usize, mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>) {
+            x[0] = T::from_f64(idx as f64);
+            x[1] = T::from_f64(2.0 * idx as f64);
+            y[0] = T::from_f64((idx % 2) as f64);
+        }
+    }
+
+    #[test]
+    fn sequential() {
+        let batches: Vec<_> = MiniBatches::new(4).iter(&Counting(10)).collect();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[0].0, arr2(&[[0.0, 0.0], [1.0, 2.0], [2.0, 4.0], [3.0, 6.0]]));
+        assert_eq!(batches[0].1, arr2(&[[0.0], [1.0], [0.0], [1.0]]));
+        assert_eq!(batches[2].0, arr2(&[[8.0, 16.0], [9.0, 18.0]]));
+
+        let batches = MiniBatches::new(4).drop_last(true).iter(&Counting(10));
+        assert_eq!(batches.len(), 2);
+        assert!(batches.into_iter().all(|(x, y)| x.rows() == 4 && y.rows() == 4));
+
+        let batches: Vec<(Array2<f32>, Array2<f32>)> = MiniBatches::new(4).iter_as(&Counting(10)).collect();
+        assert_eq!(batches[2].0, arr2(&[[8.0, 16.0], [9.0, 18.0]]));
+    }
+
+    #[test]
+    fn shuffled() {
+        let data = Counting(10);
+        let a: Vec<_> = MiniBatches::new(3).shuffle(42).iter(&data).collect();
+        let b: Vec<_> = MiniBatches::new(3).shuffle(42).iter(&data).collect();
+        assert_eq!(a, b);
+
+        let mut seen: Vec<_> = a.iter().flat_map(|&(ref x, _)| x.column(0).to_vec()).collect();
+        assert_ne!(seen, (0..10).map(|i| i as f64).collect::<Vec<_>>());
+        seen.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(seen, (0..10).map(|i| i as f64).collect::<Vec<_>>());
+
+        for (x, y) in a {
+            for (xi, yi) in x.outer_iter().zip(y.outer_iter()) {
+                assert_eq!(xi[1], 2.0 * xi[0]);
+                assert_eq!(yi[0], xi[0] % 2.0);
+            }
+        }
+    }
+}
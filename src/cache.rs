@@ -0,0 +1,301 @@
+//! Inspect and clean up the data directory
+//!
+//! Downloaded data sets accumulate in the data home (see [`common::data_home`]). This module lists
+//! the registered data sets that have files in there, reports how much disk space they use, and
+//! deletes them again. Data sets whose `data_root` was set explicitly are not covered.
+//!
+//! [`common::data_home`]: ../common/fn.data_home.html
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use mldata::cache::Cache;
+//!
+//! let cache = Cache::new().unwrap();
+//! for data_set in cache.data_sets().unwrap() {
+//!     println!("{:24} {:>12} bytes{}", data_set.entry.name, data_set.size(),
+//!              if data_set.is_complete() { "" } else { " (incomplete)" });
+//! }
+//!
+//! let removed = cache.remove("mldata/mnist-original").unwrap();
+//! println!("freed {} bytes", removed.freed);
+//! for path in removed.in_use {
+//!     println!("kept {}, which is being downloaded", path.display());
+//! }
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use common::data_home;
+use registry::{self, Entry};
+use utils::downloader::{LockFile, RemoteFile};
+use utils::error::{Context, Error};
+
+/// The data directory shared by all data sets.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    home: PathBuf,
+}
+
+impl Cache {
+    /// The cache in the data home.
+    pub fn new() -> Result<Self, Error> {
+        Ok(Cache::at(data_home()?))
+    }
+
+    /// The cache in the given directory, which is laid out like the data home.
+    pub fn at<P: AsRef<Path>>(home: P) -> Self {
+        Cache {
+            home: home.as_ref().to_owned(),
+        }
+    }
+
+    /// The directory of the cache.
+    pub fn path(&self) -> &Path {
+        &self.home
+    }
+
+    /// All registered data sets with at least one file in the cache, including partial downloads.
+    pub fn data_sets(&self) -> Result<Vec<CachedDataSet>, Error> {
+        let mut data_sets = Vec::new();
+        for entry in registry::entries() {
+            let data_set = self.status(entry)?;
+            if data_set.files.iter().any(|file| file.size.is_some() || file.partial.is_some()) {
+                data_sets.push(data_set);
+            }
+        }
+        Ok(data_sets)
+    }
+
+    /// The cached files of the data set with given name.
+    pub fn data_set(&self, name: &str) -> Result<CachedDataSet, Error> {
+        self.status(registry::find(name)?)
+    }
+
+    /// Number of bytes used by all registered data sets, including partial downloads.
+    pub fn disk_usage(&self) -> Result<u64, Error> {
+        Ok(self.data_sets()?.iter().map(CachedDataSet::size).sum())
+    }
+
+    /// Delete all files of the data set with given name, including partial downloads.
+    ///
+    /// Files that another process is downloading right now are kept and reported in
+    /// [`Removed::in_use`](struct.Removed.html#structfield.in_use). Files of other data sets in
+    /// the same directory are kept, too.
+    pub fn remove(&self, name: &str) -> Result<Removed, Error> {
+        let data_set = self.data_set(name)?;
+        let mut removed = Removed { freed: 0, in_use: Vec::new() };
+
+        for file in &data_set.files {
+            if file.size.is_none() && file.partial.is_none() && !file.lock_path().exists() {
+                continue
+            }
+
+            // hold the lock while deleting, so that no download starts in the meantime
+            let _lock = match LockFile::try_acquire(&file.lock_path())? {
+                Some(lock) => lock,
+                None => {
+                    removed.in_use.push(file.path.clone());
+                    continue
+                }
+            };
+
+            for path in &[&file.path, &file.partial_path()] {
+                match fs::remove_file(path) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                    result => result.in_file(path)?,
+                }
+            }
+            removed.freed += file.size.unwrap_or(0) + file.partial.unwrap_or(0);
+        }
+
+        self.remove_empty_dirs(data_set.entry.directory);
+        Ok(removed)
+    }
+
+    /// Delete the files of all registered data sets.
+    ///
+    /// Like [`remove`](#method.remove), files that are being downloaded are kept.
+    pub fn clear(&self) -> Result<Removed, Error> {
+        let mut removed = Removed { freed: 0, in_use: Vec::new() };
+        for entry in registry::entries() {
+            let r = self.remove(entry.name)?;
+            removed.freed += r.freed;
+            removed.in_use.extend(r.in_use);
+        }
+        Ok(removed)
+    }
+
+    fn status(&self, entry: &'static Entry) -> Result<CachedDataSet, Error> {
+        let dir = self.home.join(entry.directory);
+        let mut files = Vec::new();
+        for remote in entry.files {
+            let path = dir.join(remote.name);
+            let size = file_size(&path)?;
+            let partial = file_size(&path.with_file_name(format!("{}.part", remote.name)))?;
+            files.push(CachedFile { remote, path, size, partial });
+        }
+        Ok(CachedDataSet { entry, files })
+    }
+
+    /// Remove the directory of a data set and its parents up to the data home, as long as they
+    /// are empty.
+    fn remove_empty_dirs(&self, directory: &str) {
+        let mut dir = self.home.join(directory);
+        while dir.starts_with(&self.home) && dir != self.home {
+            if fs::remove_dir(&dir).is_err() {
+                break
+            }
+            dir.pop();
+        }
+    }
+}
+
+/// Result of deleting files from the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Removed {
+    /// Number of bytes freed
+    pub freed: u64,
+
+    /// Files that were kept because another process is downloading them
+    pub in_use: Vec<PathBuf>,
+}
+
+/// A registered data set and the state of its files in the cache.
+#[derive(Debug)]
+pub struct CachedDataSet {
+    /// The registry entry of the data set
+    pub entry: &'static Entry,
+
+    /// All files of the data set, whether present or not
+    pub files: Vec<CachedFile>,
+}
+
+impl CachedDataSet {
+    /// Number of bytes used, including partial downloads.
+    pub fn size(&self) -> u64 {
+        self.files.iter()
+            .map(|file| file.size.unwrap_or(0) + file.partial.unwrap_or(0))
+            .sum()
+    }
+
+    /// Whether all files are present.
+    ///
+    /// This only checks that the files exist; use [`verify`](#method.verify) to check their
+    /// content.
+    pub fn is_complete(&self) -> bool {
+        self.files.iter().all(|file| file.size.is_some())
+    }
+
+    /// Check that all files are present, complete and unmodified.
+    pub fn verify(&self) -> Result<(), Error> {
+        for file in &self.files {
+            file.remote.verify(&file.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// A file of a data set in the cache.
+#[derive(Debug)]
+pub struct CachedFile {
+    /// Where the file is downloaded from
+    pub remote: &'static RemoteFile,
+
+    /// Location of the file
+    pub path: PathBuf,
+
+    /// Size of the file in bytes, or `None` if it is missing
+    pub size: Option<u64>,
+
+    /// Size of an interrupted download of the file in bytes, if there is one
+    pub partial: Option<u64>,
+}
+
+impl CachedFile {
+    fn partial_path(&self) -> PathBuf {
+        self.path.with_file_name(format!("{}.part", self.remote.name))
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.path.with_file_name(format!("{}.lock", self.remote.name))
+    }
+}
+
+fn file_size(path: &Path) -> Result<Option<u64>, Error> {
+    match fs::metadata(path) {
+        Ok(meta) => Ok(Some(meta.len())),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::from(e).in_file(path)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use utils::temp::TempDir;
+    use super::*;
+
+    fn write(path: &Path, n: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::File::create(path).unwrap().write_all(&vec![b'x'; n]).unwrap();
+    }
+
+    #[test]
+    fn manage() {
+        let dir = TempDir::new("cache");
+        let home = dir.path();
+        let cache = Cache::at(&home);
+        assert!(cache.data_sets().unwrap().is_empty());
+
+        write(&home.join("UCI/iris/iris.data"), 100);
+        write(&home.join("UCI/iris/iris.names"), 20);
+        write(&home.join("mldata.org/mnist-original.hdf5.part"), 1000);
+        write(&home.join("mldata.org/regression-datasets-housing.hdf5"), 50);
+
+        let data_sets = cache.data_sets().unwrap();
+        let names: Vec<_> = data_sets.iter().map(|d| d.entry.name).collect();
+        assert_eq!(names, ["uci/iris", "mldata/regression-datasets-housing", "mldata/mnist-original"]);
+        assert_eq!(cache.disk_usage().unwrap(), 1170);
+
+        let iris = cache.data_set("uci/iris").unwrap();
+        assert!(iris.is_complete());
+        assert_eq!(iris.size(), 120);
+        iris.verify().unwrap();
+
+        let mnist = cache.data_set("mldata/mnist-original").unwrap();
+        assert!(!mnist.is_complete());
+        assert!(mnist.verify().is_err());
+        assert_eq!((mnist.files[0].size, mnist.files[0].partial), (None, Some(1000)));
+
+        // files that are being downloaded are kept
+        let lock = LockFile::try_acquire(&home.join("mldata.org/mnist-original.hdf5.lock")).unwrap().unwrap();
+        let removed = cache.remove("mldata/mnist-original").unwrap();
+        assert_eq!(removed.freed, 0);
+        assert_eq!(removed.in_use, [home.join("mldata.org/mnist-original.hdf5")]);
+        assert!(home.join("mldata.org/mnist-original.hdf5.part").exists());
+        drop(lock);
+
+        assert_eq!(cache.remove("mldata/mnist-original").unwrap().freed, 1000);
+        assert!(!home.join("mldata.org/mnist-original.hdf5.lock").exists());
+        assert!(home.join("mldata.org/regression-datasets-housing.hdf5").exists());
+
+        write(&home.join("UCI/iris/iris.names"), 0);
+        assert!(cache.data_set("uci/iris").unwrap().verify().is_err());
+
+        assert_eq!(cache.remove("uci/iris").unwrap().freed, 100);
+        assert!(!home.join("UCI").exists());
+
+        assert_eq!(cache.clear().unwrap().freed, 50);
+        assert!(cache.data_sets().unwrap().is_empty());
+        assert!(!home.join("mldata.org").exists());
+
+        match cache.remove("uci/no-such-data") {
+            Err(Error::UnknownDataSet(_)) => {}
+            _ => panic!("expected unknown data set error"),
+        }
+    }
+}
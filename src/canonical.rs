@@ -1,6 +1,6 @@
 //! Home module of the canonical data representation
 
-use ndarray::Array2;
+use ndarray::{Array2, ArrayView2, ArrayViewMut1};
 
 /// Conversion into canonical data representation.
 ///
@@ -10,6 +10,11 @@ use ndarray::Array2;
 ///
 /// This representation was chosen to be generic. It should be possible to represent most data sets
 /// using `f64` and missing data can be encoded as NaN.
+///
+/// This trait is implemented for all data sets that implement [`CanonicalDataAs<f64>`], which
+/// converts into other element types, too.
+///
+/// [`CanonicalDataAs<f64>`]: trait.CanonicalDataAs.html
 pub trait CanonicalData {
     fn to_canonical(&self) -> (Array2<f64>, Array2<f64>);
 
@@ -19,3 +24,101 @@ pub trait CanonicalData {
         self.to_canonical()
     }
 }
+
+impl<D: CanonicalDataAs<f64>> CanonicalData for D {
+    fn to_canonical(&self) -> (Array2<f64>, Array2<f64>) {
+        self.to_canonical_as()
+    }
+}
+
+/// Conversion into canonical data representation with element type `T`.
+///
+/// Every data set converts into [`Real`] types (`f32` and `f64`). Data sets that are natively
+/// stored as `u8`, such as images, additionally convert into any type that represents `u8`
+/// losslessly (e.g. `u8`, `u16`, `i32`).
+///
+/// [`Real`]: trait.Real.html
+pub trait CanonicalDataAs<T> {
+    fn to_canonical_as(&self) -> (Array2<T>, Array2<T>);
+
+    fn into_canonical_as(self) -> (Array2<T>, Array2<T>)
+        where Self: Sized
+    {
+        self.to_canonical_as()
+    }
+}
+
+/// Sample-wise conversion into canonical data representation.
+///
+/// This allows to build parts of the canonical representation, such as mini-batches, directly
+/// from the native storage of a data set without converting the whole data set first.
+pub trait CanonicalSamples<T = f64> {
+    /// Shape of the canonical representation: number of samples and number of columns in X and Y.
+    fn canonical_shape(&self) -> (usize, usize, usize);
+
+    /// Write the canonical representation of sample `idx` into a row of X and a row of Y.
+    fn write_canonical(&self, idx: usize, x: ArrayViewMut1<T>, y: ArrayViewMut1<T>);
+
+    /// Canonical representation of the samples at the given indices.
+    fn select_canonical(&self, idx: &[usize]) -> (Array2<T>, Array2<T>)
+        where T: Clone + Default
+    {
+        let (_, n_x, n_y) = self.canonical_shape();
+        let mut x = Array2::from_elem((idx.len(), n_x), T::default());
+        let mut y = Array2::from_elem((idx.len(), n_y), T::default());
+        for (row, &i) in idx.iter().enumerate() {
+            self.write_canonical(i, x.row_mut(row), y.row_mut(row));
+        }
+        (x, y)
+    }
+}
+
+/// Floating point element types of the canonical representation.
+///
+/// Conversion from native types that are wider than the element type (e.g. `f64` data into
+/// `f32`) rounds to the nearest representable value.
+pub trait Real: Copy + Default + PartialEq + From<u8> + From<f32> {
+    fn from_f64(x: f64) -> Self;
+    fn from_i32(x: i32) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+impl Real for f32 {
+    fn from_f64(x: f64) -> Self {
+        x as f32
+    }
+
+    fn from_i32(x: i32) -> Self {
+        x as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Real for f64 {
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+
+    fn from_i32(x: i32) -> Self {
+        x as f64
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// Borrowed canonical representation in the native element type.
+///
+/// Implemented by data sets whose storage layout allows to view the data in canonical
+/// representation without copying. The views may be strided, i.e. not contiguous in memory.
+pub trait CanonicalView {
+    /// Native element type of the data set.
+    type Elem;
+
+    /// View features (X) and target variables (Y) in canonical representation.
+    fn canonical_view(&self) -> (ArrayView2<Self::Elem>, ArrayView2<Self::Elem>);
+}
@@ -1,5 +1,76 @@
 //! Common stuff for all data sets
 
-use app_dirs::AppInfo;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use app_dirs::{get_app_root, AppDataType, AppInfo};
+
+use utils::downloader::{Downloader, RemoteFile};
+use utils::error::Error;
 
 pub const APP_INFO: AppInfo = AppInfo{name: "mldata", author: "mldata"};
+
+/// Environment variable that overrides the default data directory of all data sets.
+pub const DATA_HOME_VAR: &str = "MLDATA_HOME";
+
+static DATA_HOME: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Set the data directory of all data sets, or restore the default with `None`.
+///
+/// This takes precedence over the `MLDATA_HOME` environment variable. It does not affect
+/// `DataSet` builders whose `data_root` has been set explicitly.
+pub fn set_data_home<P: AsRef<Path>>(path: Option<P>) {
+    *DATA_HOME.lock().unwrap() = path.map(|p| p.as_ref().to_owned());
+}
+
+/// The directory that contains the data of all data sets.
+///
+/// This is the directory set with [`set_data_home`](fn.set_data_home.html), or else the value of
+/// the `MLDATA_HOME` environment variable, or else the user data directory of the platform.
+pub fn data_home() -> Result<PathBuf, Error> {
+    let path = DATA_HOME.lock().unwrap().clone();
+    resolve_data_home(path, env::var_os(DATA_HOME_VAR))
+}
+
+/// The data directory of a single data set, e.g. `data_dir("UCI/iris")`.
+pub fn data_dir(subdir: &str) -> Result<PathBuf, Error> {
+    Ok(data_home()?.join(subdir))
+}
+
+/// Fail with `Error::NoMirror` unless `file` is in the data directory or can be downloaded.
+///
+/// The files of the `mldata_*` data sets declare no URLs because mldata.org is offline, so they can
+/// only be downloaded from a mirror.
+pub fn require_mirror(data_set: &'static str, file: &Path, remote: &RemoteFile, downloader: &Downloader) -> Result<(), Error> {
+    if file.exists() || !downloader.urls(remote).is_empty() {
+        Ok(())
+    } else {
+        Err(Error::NoMirror { data_set, file: remote.name })
+    }
+}
+
+fn resolve_data_home(path: Option<PathBuf>, var: Option<OsString>) -> Result<PathBuf, Error> {
+    match (path, var) {
+        (Some(path), _) => Ok(path),
+        (None, Some(ref var)) if !var.is_empty() => Ok(PathBuf::from(var)),
+        _ => Ok(get_app_root(AppDataType::UserData, &APP_INFO)?),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        let path = Some(PathBuf::from("/data/override"));
+        let var = Some(OsString::from("/data/env"));
+
+        assert_eq!(resolve_data_home(path.clone(), var.clone()).unwrap(), PathBuf::from("/data/override"));
+        assert_eq!(resolve_data_home(None, var).unwrap(), PathBuf::from("/data/env"));
+        assert_eq!(resolve_data_home(None, Some(OsString::new())).ok(), get_app_root(AppDataType::UserData, &APP_INFO).ok());
+    }
+}
@@ -0,0 +1,144 @@
+//! Unified access to all data sets
+//!
+//! Every data set module provides its own `DataSet`, `DataSetLoader` and `Data` types. The traits
+//! in this module tie them together, so that generic code can be written once and used with any
+//! data set.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use mldata::dataset::Dataset;
+//! use mldata::uci_iris;
+//!
+//! fn describe<D: Dataset>() {
+//!     let data = D::load().unwrap();
+//!     println!("{} samples", data.n_samples());
+//! }
+//!
+//! describe::<uci_iris::Data>();
+//! ```
+
+use utils::error::{Context, Error};
+
+/// Part of a data set.
+///
+/// Some data sets come with an official partitioning into training and testing data. Using it
+/// makes results comparable to published numbers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Split {
+    /// The complete data set
+    All,
+
+    /// The official training set
+    Train,
+
+    /// The official testing set
+    Test,
+
+    /// The official validation set, for data sets that set aside data for model selection
+    Validation,
+}
+
+/// Common interface of the in-memory representation of all data sets.
+pub trait Dataset: Sized {
+    /// Stable name under which the data set is registered, e.g. `"uci/iris"`.
+    const NAME: &'static str;
+
+    /// Features of a single sample, as returned by `get_sample`.
+    type Features<'a> where Self: 'a;
+
+    /// Target variable(s) of a single sample, as returned by `get_sample`.
+    type Target;
+
+    /// The loader that produces this data set.
+    type Loader: Loader<Data=Self>;
+
+    /// Create a loader with default settings.
+    ///
+    /// This will download the data when run for the first time.
+    fn loader() -> Result<Self::Loader, Error>;
+
+    /// Load the data set with default settings.
+    ///
+    /// Errors carry the name of the data set as context.
+    fn load() -> Result<Self, Error> {
+        Self::loader().and_then(|l| l.load_data()).in_data_set(Self::NAME)
+    }
+
+    /// Load part of the data set with default settings.
+    fn load_split(split: Split) -> Result<Self, Error> {
+        Self::loader().and_then(|l| l.load_split(split)).in_data_set(Self::NAME)
+    }
+
+    /// Load the description of the data set with default settings.
+    fn load_info() -> Result<String, Error> {
+        Self::loader().and_then(|l| l.load_info()).in_data_set(Self::NAME)
+    }
+
+    /// Number of samples in the data set.
+    fn n_samples(&self) -> usize;
+
+    /// Get features and target of the sample at position `idx`.
+    fn get_sample(&self, idx: usize) -> (Self::Features<'_>, Self::Target);
+}
+
+/// Common interface of all data set loaders.
+pub trait Loader {
+    /// In-memory representation of the data set
+    type Data;
+
+    /// Load the description of the data set.
+    fn load_info(&self) -> Result<String, Error>;
+
+    /// Load the complete data set.
+    fn load_data(&self) -> Result<Self::Data, Error>;
+
+    /// Check that all files of the data set are complete and unmodified.
+    fn verify(&self) -> Result<(), Error>;
+
+    /// Whether the data set comes with an official training/testing split.
+    fn has_official_split(&self) -> bool {
+        false
+    }
+
+    /// Load part of the data set.
+    ///
+    /// Data sets without official split only support `Split::All`; anything else results in
+    /// `Error::NoOfficialSplit`.
+    fn load_split(&self, split: Split) -> Result<Self::Data, Error> {
+        match split {
+            Split::All => self.load_data(),
+            _ => Err(Error::NoOfficialSplit),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uci_iris;
+
+    fn first_and_last<D: Dataset>(data: &D) -> (D::Target, D::Target) {
+        (data.get_sample(0).1, data.get_sample(data.n_samples() - 1).1)
+    }
+
+    #[test]
+    fn generic() {
+        let data = uci_iris::Data::load().unwrap();
+        assert_eq!(Dataset::n_samples(&data), 150);
+        assert_eq!(first_and_last(&data), (uci_iris::Iris::Setosa, uci_iris::Iris::Virginica));
+        assert!(uci_iris::Data::load_info().unwrap().contains("Iris"));
+    }
+
+    #[test]
+    fn no_official_split() {
+        let loader = uci_iris::Data::loader().unwrap();
+        assert!(!loader.has_official_split());
+        assert_eq!(loader.load_split(Split::All).unwrap().n_samples(), 150);
+        match loader.load_split(Split::Train) {
+            Err(Error::NoOfficialSplit) => {}
+            _ => panic!("expected error"),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Configurable encoding of categorical variables
+//!
+//! By default, the canonical representation contains the native codes of categorical variables
+//! (e.g. 0, 1, 2 for the three Iris species). An [`Encoder`](struct.Encoder.html) converts them
+//! into ordinal or one-hot encoding, or drops them, separately for each feature and for the
+//! targets. Boolean variables are treated as categorical variables with the levels `false` and
+//! `true`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate ndarray;
+//! # extern crate mldata;
+//! use ndarray::Array2;
+//! use mldata::encoding::{Encoder, Encoding};
+//! use mldata::uci_auto_mpg::DataSet;
+//!
+//! # fn main() {
+//! let data = DataSet::new().create().unwrap().load_data().unwrap();
+//!
+//! let (x, y): (Array2<f32>, _) = Encoder::new()
+//!     .feature("origin", Encoding::OneHot)
+//!     .to_canonical(&data)
+//!     .unwrap();
+//!
+//! // origin is replaced by three columns (USA, Europe, Japan)
+//! assert_eq!(x.cols(), 9);
+//! # }
+//! ```
+
+use ndarray::{Array2, ArrayView1, Axis};
+
+use canonical::{CanonicalDataAs, Real};
+use schema::{Column, HasSchema, Kind, Level, Schema};
+use utils::error::Error;
+
+/// Representation of a categorical variable.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Encoding {
+    /// Keep the native codes, as produced by `to_canonical`.
+    Native,
+
+    /// Replace the native codes by the position of the level: 0, 1, ..., n_levels - 1.
+    Ordinal,
+
+    /// One column per level, containing 1 if the sample belongs to this level and 0 otherwise.
+    OneHot,
+
+    /// Remove the column.
+    Drop,
+}
+
+/// Configure the encoding of categorical variables.
+///
+/// This structure implements the builder pattern. Values that do not correspond to a level,
+/// such as missing values, are encoded as NaN.
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    categorical: Encoding,
+    features: Vec<(String, Encoding)>,
+    target: Encoding,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Encoder::new()
+    }
+}
+
+impl Encoder {
+    /// Keep the native codes of all variables.
+    pub fn new() -> Self {
+        Encoder {
+            categorical: Encoding::Native,
+            features: Vec::new(),
+            target: Encoding::Native,
+        }
+    }
+
+    /// Encoding of all categorical and boolean features without explicit setting.
+    pub fn categorical(&mut self, encoding: Encoding) -> &mut Self {
+        self.categorical = encoding;
+        self
+    }
+
+    /// Encoding of the feature with given name.
+    ///
+    /// Only `Native` and `Drop` are allowed for features that are neither categorical nor
+    /// boolean.
+    pub fn feature<S: Into<String>>(&mut self, name: S, encoding: Encoding) -> &mut Self {
+        let name = name.into();
+        self.features.retain(|&(ref n, _)| *n != name);
+        self.features.push((name, encoding));
+        self
+    }
+
+    /// Encoding of the target variables.
+    ///
+    /// Use `Encoding::OneHot` to obtain class indicator matrices for classification.
+    pub fn target(&mut self, encoding: Encoding) -> &mut Self {
+        self.target = encoding;
+        self
+    }
+
+    /// Convert a data set into canonical representation with the configured encoding.
+    pub fn to_canonical<T, D>(&self, data: &D) -> Result<(Array2<T>, Array2<T>), Error>
+        where T: Real,
+              D: CanonicalDataAs<T> + HasSchema
+    {
+        let (x, y) = data.to_canonical_as();
+        self.encode(&D::schema(), &x, &y)
+    }
+
+    /// Encode the canonical representation `x`, `y` of a data set with the given schema.
+    pub fn encode<T: Real>(&self, schema: &Schema, x: &Array2<T>, y: &Array2<T>) -> Result<(Array2<T>, Array2<T>), Error> {
+        let x = encode_columns(&schema.features, &self.feature_encodings(schema)?, x)?;
+        let y = encode_columns(&schema.targets, &vec![self.target; schema.targets.len()], y)?;
+        Ok((x, y))
+    }
+
+    /// Describe the columns after encoding a data set with the given schema.
+    pub fn encode_schema(&self, schema: &Schema) -> Result<Schema, Error> {
+        let features = self.feature_encodings(schema)?;
+        let targets = vec![self.target; schema.targets.len()];
+        Ok(Schema {
+            features: encode_schema_columns(&schema.features, &features)?,
+            targets: encode_schema_columns(&schema.targets, &targets)?,
+        })
+    }
+
+    fn feature_encodings(&self, schema: &Schema) -> Result<Vec<Encoding>, Error> {
+        for &(ref name, _) in &self.features {
+            if schema.feature(name).is_none() {
+                return Err(Error::UnknownColumn(name.clone()))
+            }
+        }
+
+        let encodings = schema.features
+            .iter()
+            .map(|column| {
+                match self.features.iter().find(|&&(ref n, _)| *n == column.name) {
+                    Some(&(_, encoding)) => encoding,
+                    None if levels(column).is_some() => self.categorical,
+                    None => Encoding::Native,
+                }
+            })
+            .collect();
+        Ok(encodings)
+    }
+}
+
+/// Levels of a categorical or boolean column.
+fn levels(column: &Column) -> Option<Vec<Level>> {
+    match column.kind {
+        Kind::Categorical(ref levels) => Some(levels.clone()),
+        Kind::Boolean => Some(vec![
+            Level { code: 0, name: "false".to_owned() },
+            Level { code: 1, name: "true".to_owned() },
+        ]),
+        _ => None,
+    }
+}
+
+/// Levels of a column that is to be encoded, or an error if the encoding requires levels.
+fn levels_for(column: &Column, encoding: Encoding) -> Result<Vec<Level>, Error> {
+    match (levels(column), encoding) {
+        (Some(levels), _) => Ok(levels),
+        (None, Encoding::Native) | (None, Encoding::Drop) => Ok(Vec::new()),
+        (None, _) => Err(Error::NotCategorical(column.name.clone())),
+    }
+}
+
+fn encode_schema_columns(columns: &[Column], encodings: &[Encoding]) -> Result<Vec<Column>, Error> {
+    let mut output = Vec::new();
+    for (column, &encoding) in columns.iter().zip(encodings) {
+        let levels = levels_for(column, encoding)?;
+        match encoding {
+            Encoding::Native => output.push(column.clone()),
+            Encoding::Ordinal => {
+                let names: Vec<_> = levels.iter().map(|l| l.name.as_str()).collect();
+                output.push(Column {
+                    kind: Kind::categorical(&names[..]),
+                    ..column.clone()
+                })
+            }
+            Encoding::OneHot => {
+                for level in levels {
+                    let name = format!("{}={}", column.name, level.name);
+                    let description = format!("{} is {}", column.description, level.name);
+                    output.push(Column::new(name, description, Kind::Boolean));
+                }
+            }
+            Encoding::Drop => {}
+        }
+    }
+    Ok(output)
+}
+
+fn encode_columns<T: Real>(columns: &[Column], encodings: &[Encoding], data: &Array2<T>) -> Result<Array2<T>, Error> {
+    assert_eq!(columns.len(), data.cols(), "schema does not match the data");
+
+    let mut output: Vec<Vec<T>> = Vec::new();
+    for ((column, &encoding), values) in columns.iter().zip(encodings).zip(data.axis_iter(Axis(1))) {
+        let levels = levels_for(column, encoding)?;
+        match encoding {
+            Encoding::Native => output.push(values.to_vec()),
+            Encoding::Ordinal => output.push(values.iter().map(|&v| ordinal(&levels, v)).collect()),
+            Encoding::OneHot => {
+                for level in &levels {
+                    output.push(values.iter().map(|&v| indicator(&levels, level, v)).collect());
+                }
+            }
+            Encoding::Drop => {}
+        }
+    }
+
+    let mut result = Array2::from_elem((data.rows(), output.len()), T::default());
+    for (mut target, source) in result.axis_iter_mut(Axis(1)).zip(output) {
+        target.assign(&ArrayView1::from(&source[..]));
+    }
+    Ok(result)
+}
+
+fn position<T: Real>(levels: &[Level], value: T) -> Option<usize> {
+    levels.iter().position(|l| T::from_i32(l.code) == value)
+}
+
+fn ordinal<T: Real>(levels: &[Level], value: T) -> T {
+    match position(levels, value) {
+        Some(i) => T::from_i32(i as i32),
+        None => T::from_f64(f64::NAN),
+    }
+}
+
+fn indicator<T: Real>(levels: &[Level], level: &Level, value: T) -> T {
+    match position(levels, value) {
+        Some(_) if T::from_i32(level.code) == value => T::from(1u8),
+        Some(_) => T::from(0u8),
+        None => T::from_f64(f64::NAN),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema {
+            features: vec![
+                Column::new("weight", "weight", Kind::Continuous),
+                Column::new("origin", "origin", Kind::categorical_from(1, &["USA", "Europe", "Japan"])),
+                Column::new("diesel", "diesel engine", Kind::Boolean),
+            ],
+            targets: vec![
+                Column::new("class", "class", Kind::categorical(&["a", "b"])),
+            ],
+        }
+    }
+
+    fn data() -> (Array2<f64>, Array2<f64>) {
+        let x = arr2(&[[1.5, 3.0, 0.0],
+                       [2.5, 1.0, 1.0],
+                       [3.5, f64::NAN, 0.0]]);
+        let y = arr2(&[[1.0], [0.0], [1.0]]);
+        (x, y)
+    }
+
+    #[test]
+    fn native() {
+        let (x, y) = data();
+        let (xe, ye) = Encoder::new().encode(&schema(), &x, &y).unwrap();
+        assert_eq!(xe.row(0), x.row(0));
+        assert_eq!(ye, y);
+    }
+
+    #[test]
+    fn one_hot() {
+        let (x, y) = data();
+        let (xe, ye) = Encoder::new()
+            .categorical(Encoding::OneHot)
+            .target(Encoding::OneHot)
+            .encode(&schema(), &x, &y).unwrap();
+
+        assert_eq!(xe.shape(), [3, 6]);
+        assert_eq!(xe.row(0), arr2(&[[1.5, 0.0, 0.0, 1.0, 1.0, 0.0]]).row(0));
+        assert_eq!(xe.row(1), arr2(&[[2.5, 1.0, 0.0, 0.0, 0.0, 1.0]]).row(0));
+        assert!(xe[[2, 1]].is_nan() && xe[[2, 2]].is_nan() && xe[[2, 3]].is_nan());
+        assert_eq!(ye, arr2(&[[0.0, 1.0], [1.0, 0.0], [0.0, 1.0]]));
+
+        let names = Encoder::new().categorical(Encoding::OneHot).encode_schema(&schema()).unwrap();
+        assert_eq!(names.feature_names(), ["weight", "origin=USA", "origin=Europe", "origin=Japan",
+                                           "diesel=false", "diesel=true"]);
+    }
+
+    #[test]
+    fn per_feature() {
+        let (x, y) = data();
+        let (xe, _) = Encoder::new()
+            .categorical(Encoding::OneHot)
+            .feature("origin", Encoding::Ordinal)
+            .feature("weight", Encoding::Drop)
+            .encode(&schema(), &x, &y).unwrap();
+
+        assert_eq!(xe.shape(), [3, 3]);
+        assert_eq!(xe.column(0).slice(s![..2]), arr2(&[[2.0, 0.0]]).row(0));
+        assert!(xe[[2, 0]].is_nan());
+        assert_eq!(xe.column(2), arr2(&[[0.0, 1.0, 0.0]]).row(0));
+    }
+
+    #[test]
+    fn errors() {
+        let (x, y) = data();
+        match Encoder::new().feature("weight", Encoding::OneHot).encode(&schema(), &x, &y) {
+            Err(Error::NotCategorical(ref name)) => assert_eq!(name, "weight"),
+            _ => panic!("expected error"),
+        }
+        match Encoder::new().feature("colour", Encoding::Drop).encode(&schema(), &x, &y) {
+            Err(Error::UnknownColumn(ref name)) => assert_eq!(name, "colour"),
+            _ => panic!("expected error"),
+        }
+    }
+}
@@ -1,21 +1,27 @@
 //! Load (and download) machine learning data sets
 
 extern crate app_dirs;
-extern crate hdf5_sys;
+extern crate flate2;
+extern crate fs2;
 
-// workaround to supress warning that macro_use is unused; it is used in some tests, though.
-#[cfg(test)]
 #[macro_use(s)]
 extern crate ndarray;
-#[cfg(not(test))]
-extern crate ndarray;
-
-extern crate num;
 
 extern crate reqwest;
+extern crate sha2;
 
+pub mod batch;
+pub mod cache;
 pub mod canonical;
 pub mod common;
+pub mod dataset;
+pub mod encoding;
+pub mod missing;
+pub mod registry;
+pub mod schema;
+pub mod split;
+
+#[macro_use]
 pub mod utils;
 
 pub mod mldata_auto_mpg;
@@ -0,0 +1,369 @@
+//! Handling of missing values
+//!
+//! Missing values are represented as NaN in canonical representation (e.g. the unknown
+//! horsepower of some cars in the Auto MPG data sets). This module reports where values are
+//! missing and provides strategies to deal with them: keep them as NaN, drop the affected
+//! samples, or impute the mean, median or mode of the column. Optionally, an indicator column
+//! is added for each feature that had missing values.
+//!
+//! Missing values should be handled before encoding categorical variables, so that the
+//! imputed values are valid levels.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate ndarray;
+//! # extern crate mldata;
+//! use ndarray::Array2;
+//! use mldata::canonical::CanonicalData;
+//! use mldata::encoding::{Encoder, Encoding};
+//! use mldata::missing::{self, MissingValues, Strategy};
+//! use mldata::schema::HasSchema;
+//! use mldata::uci_auto_mpg::{Data, DataSet};
+//!
+//! # fn main() {
+//! let data = DataSet::new().create().unwrap().load_data().unwrap();
+//!
+//! let report = missing::missing_values(&data);
+//! println!("missing values in {:?}, samples {:?}", report.columns, report.rows);
+//!
+//! let (x, y): (Array2<f64>, Array2<f64>) = data.into_canonical();
+//! let (x, y, schema) = MissingValues::new()
+//!     .strategy(Strategy::Median)
+//!     .indicator(true)
+//!     .apply(&Data::schema(), &x, &y)
+//!     .unwrap();
+//!
+//! let (x, y) = Encoder::new()
+//!     .categorical(Encoding::OneHot)
+//!     .encode(&schema, &x, &y)
+//!     .unwrap();
+//! # }
+//! ```
+
+use ndarray::{Array2, Axis};
+
+use canonical::{CanonicalData, CanonicalDataAs, Real};
+use schema::{Column, HasSchema, Kind, Schema};
+use utils::error::Error;
+
+/// What to do with missing feature values.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Strategy {
+    /// Keep missing values as NaN.
+    Keep,
+
+    /// Remove samples that contain missing values.
+    DropRows,
+
+    /// Replace missing values by the mean of the column.
+    Mean,
+
+    /// Replace missing values by the median of the column.
+    Median,
+
+    /// Replace missing values by the most frequent value of the column.
+    Mode,
+}
+
+/// Where values are missing in a data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Names of the features with missing values, and the number of missing values
+    pub columns: Vec<(String, usize)>,
+
+    /// Indices of the samples with at least one missing feature
+    pub rows: Vec<usize>,
+}
+
+/// Find missing values in a data set.
+pub fn missing_values<D: CanonicalData + HasSchema>(data: &D) -> Report {
+    let (x, _) = data.to_canonical();
+    report(&D::schema(), &x)
+}
+
+/// Find missing values in the features `x` of a data set with the given schema.
+pub fn report<T: Real>(schema: &Schema, x: &Array2<T>) -> Report {
+    assert_eq!(schema.features.len(), x.cols(), "schema does not match the data");
+
+    let columns = schema.features
+        .iter()
+        .zip(x.axis_iter(Axis(1)))
+        .map(|(column, values)| (column.name.clone(), values.iter().filter(|&&v| is_missing(v)).count()))
+        .filter(|&(_, n)| n > 0)
+        .collect();
+
+    let rows = x.outer_iter()
+        .enumerate()
+        .filter(|&(_, row)| row.iter().any(|&v| is_missing(v)))
+        .map(|(i, _)| i)
+        .collect();
+
+    Report {
+        columns,
+        rows,
+    }
+}
+
+/// Configure the handling of missing values.
+///
+/// This structure implements the builder pattern.
+#[derive(Debug, Clone)]
+pub struct MissingValues {
+    strategy: Strategy,
+    columns: Vec<(String, Strategy)>,
+    indicator: bool,
+}
+
+impl Default for MissingValues {
+    fn default() -> Self {
+        MissingValues::new()
+    }
+}
+
+impl MissingValues {
+    /// Keep all missing values as NaN.
+    pub fn new() -> Self {
+        MissingValues {
+            strategy: Strategy::Keep,
+            columns: Vec::new(),
+            indicator: false,
+        }
+    }
+
+    /// Strategy for all features without explicit setting.
+    pub fn strategy(&mut self, strategy: Strategy) -> &mut Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Strategy for the feature with given name.
+    pub fn column<S: Into<String>>(&mut self, name: S, strategy: Strategy) -> &mut Self {
+        let name = name.into();
+        self.columns.retain(|&(ref n, _)| *n != name);
+        self.columns.push((name, strategy));
+        self
+    }
+
+    /// Append an indicator column for each feature with missing values.
+    ///
+    /// The indicator is 1 where the value was missing and 0 otherwise.
+    pub fn indicator(&mut self, b: bool) -> &mut Self {
+        self.indicator = b;
+        self
+    }
+
+    /// Convert a data set into canonical representation and handle missing values.
+    pub fn to_canonical<T, D>(&self, data: &D) -> Result<(Array2<T>, Array2<T>), Error>
+        where T: Real,
+              D: CanonicalDataAs<T> + HasSchema
+    {
+        let (x, y) = data.to_canonical_as();
+        let (x, y, _) = self.apply(&D::schema(), &x, &y)?;
+        Ok((x, y))
+    }
+
+    /// Handle missing values in the canonical representation `x`, `y` of a data set with the
+    /// given schema.
+    ///
+    /// Returns the new canonical representation and its schema, which differs from the original
+    /// schema if indicator columns were added.
+    pub fn apply<T: Real>(&self, schema: &Schema, x: &Array2<T>, y: &Array2<T>) -> Result<(Array2<T>, Array2<T>, Schema), Error> {
+        assert_eq!(schema.features.len(), x.cols(), "schema does not match the data");
+
+        let strategies = self.column_strategies(schema)?;
+
+        let keep: Vec<usize> = x.outer_iter()
+            .enumerate()
+            .filter(|&(_, row)| {
+                row.iter()
+                    .zip(&strategies)
+                    .all(|(&v, &s)| s != Strategy::DropRows || !is_missing(v))
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut x_out = x.select(Axis(0), &keep);
+        let y_out = y.select(Axis(0), &keep);
+
+        let mut indicators = Vec::new();
+        for (j, (column, &strategy)) in schema.features.iter().zip(&strategies).enumerate() {
+            let mut values = x_out.column_mut(j);
+            let missing: Vec<bool> = values.iter().map(|&v| is_missing(v)).collect();
+            if !missing.contains(&true) {
+                continue
+            }
+
+            if self.indicator {
+                indicators.push((column, missing));
+            }
+
+            let fill = match strategy {
+                Strategy::Keep | Strategy::DropRows => continue,
+                Strategy::Mean => mean(values.iter().cloned()),
+                Strategy::Median => median(values.iter().cloned()),
+                Strategy::Mode => mode(values.iter().cloned()),
+            };
+
+            for v in values.iter_mut().filter(|v| is_missing(**v)) {
+                *v = T::from_f64(fill);
+            }
+        }
+
+        let mut features = schema.features.clone();
+        if !indicators.is_empty() {
+            let n_cols = x_out.cols();
+            let mut extended = Array2::from_elem((x_out.rows(), n_cols + indicators.len()), T::default());
+            extended.slice_mut(s![.., ..n_cols]).assign(&x_out);
+            for (k, (column, missing)) in indicators.into_iter().enumerate() {
+                for (out, m) in extended.column_mut(n_cols + k).iter_mut().zip(missing) {
+                    *out = T::from(m as u8);
+                }
+                features.push(Column::new(format!("{}_missing", column.name),
+                                          format!("{} is missing", column.description),
+                                          Kind::Boolean));
+            }
+            x_out = extended;
+        }
+
+        let schema = Schema {
+            features,
+            targets: schema.targets.clone(),
+        };
+
+        Ok((x_out, y_out, schema))
+    }
+
+    fn column_strategies(&self, schema: &Schema) -> Result<Vec<Strategy>, Error> {
+        for &(ref name, _) in &self.columns {
+            if schema.feature(name).is_none() {
+                return Err(Error::UnknownColumn(name.clone()))
+            }
+        }
+
+        let strategies = schema.features
+            .iter()
+            .map(|column| {
+                self.columns.iter()
+                    .find(|&&(ref n, _)| *n == column.name)
+                    .map(|&(_, s)| s)
+                    .unwrap_or(self.strategy)
+            })
+            .collect();
+        Ok(strategies)
+    }
+}
+
+fn is_missing<T: Real>(v: T) -> bool {
+    v.to_f64().is_nan()
+}
+
+/// Values that are not missing, in ascending order.
+fn sorted_values<T: Real, I: Iterator<Item=T>>(values: I) -> Vec<f64> {
+    let mut v: Vec<f64> = values.filter(|&v| !is_missing(v)).map(T::to_f64).collect();
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    v
+}
+
+fn mean<T: Real, I: Iterator<Item=T>>(values: I) -> f64 {
+    let v = sorted_values(values);
+    v.iter().sum::<f64>() / v.len() as f64
+}
+
+fn median<T: Real, I: Iterator<Item=T>>(values: I) -> f64 {
+    let v = sorted_values(values);
+    match v.len() {
+        0 => f64::NAN,
+        n if n % 2 == 1 => v[n / 2],
+        n => (v[n / 2 - 1] + v[n / 2]) / 2.0,
+    }
+}
+
+/// Most frequent value; the smallest one if there are several.
+fn mode<T: Real, I: Iterator<Item=T>>(values: I) -> f64 {
+    let v = sorted_values(values);
+    let mut best = (f64::NAN, 0);
+    let mut i = 0;
+    while i < v.len() {
+        let n = v[i..].iter().take_while(|&&x| x == v[i]).count();
+        if n > best.1 {
+            best = (v[i], n);
+        }
+        i += n;
+    }
+    best.0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema {
+            features: vec![
+                Column::new("a", "a", Kind::Continuous),
+                Column::new("b", "b", Kind::Integer),
+            ],
+            targets: vec![
+                Column::new("t", "t", Kind::Continuous),
+            ],
+        }
+    }
+
+    fn data() -> (Array2<f64>, Array2<f64>) {
+        let x = arr2(&[[1.0, 5.0],
+                       [f64::NAN, 5.0],
+                       [2.0, f64::NAN],
+                       [6.0, 7.0]]);
+        let y = arr2(&[[0.0], [1.0], [2.0], [3.0]]);
+        (x, y)
+    }
+
+    #[test]
+    fn find_missing() {
+        let rep = report(&schema(), &data().0);
+        assert_eq!(rep.columns, [("a".to_owned(), 1), ("b".to_owned(), 1)]);
+        assert_eq!(rep.rows, [1, 2]);
+    }
+
+    #[test]
+    fn drop_rows() {
+        let (x, y) = data();
+        let (xm, ym, _) = MissingValues::new()
+            .column("a", Strategy::DropRows)
+            .apply(&schema(), &x, &y).unwrap();
+        assert_eq!(xm.rows(), 3);
+        assert_eq!(ym, arr2(&[[0.0], [2.0], [3.0]]));
+        assert!(xm[[1, 1]].is_nan());
+    }
+
+    #[test]
+    fn impute() {
+        let (x, y) = data();
+        let (xm, _, _) = MissingValues::new().strategy(Strategy::Mean).apply(&schema(), &x, &y).unwrap();
+        assert_eq!(xm.column(0), arr2(&[[1.0, 3.0, 2.0, 6.0]]).row(0));
+
+        let (xm, _, _) = MissingValues::new().strategy(Strategy::Median).apply(&schema(), &x, &y).unwrap();
+        assert_eq!(xm.column(0), arr2(&[[1.0, 2.0, 2.0, 6.0]]).row(0));
+
+        let (xm, _, _) = MissingValues::new().strategy(Strategy::Mode).apply(&schema(), &x, &y).unwrap();
+        assert_eq!(xm.column(1), arr2(&[[5.0, 5.0, 5.0, 7.0]]).row(0));
+    }
+
+    #[test]
+    fn indicator() {
+        let (x, y) = data();
+        let (xm, _, schema) = MissingValues::new()
+            .strategy(Strategy::Median)
+            .column("b", Strategy::Keep)
+            .indicator(true)
+            .apply(&schema(), &x, &y).unwrap();
+
+        assert_eq!(schema.feature_names(), ["a", "b", "a_missing", "b_missing"]);
+        assert_eq!(xm.column(2), arr2(&[[0.0, 1.0, 0.0, 0.0]]).row(0));
+        assert_eq!(xm.column(3), arr2(&[[0.0, 0.0, 1.0, 0.0]]).row(0));
+        assert!(xm[[2, 1]].is_nan());
+    }
+}
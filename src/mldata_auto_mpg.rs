@@ -1,41 +1,88 @@
 //! The "Auto MPG" data set from mldata.org.
+//!
+//! mldata.org is no longer online and no other public copy of its HDF5 files is known. To download
+//! the data, configure a mirror that provides `uci-20070111-autompg.hdf5`, either with
+//! [`DataSet::mirror`](struct.DataSet.html#method.mirror) or the `MLDATA_MIRRORS` environment
+//! variable. Otherwise, loading fails with `Error::NoMirror` unless the file is already in the
+//! data directory.
 
 use std::fs;
 use std::path;
 
-use app_dirs::*;
-use ndarray::{Array2, Zip};
+use ndarray::{Array2, ArrayViewMut1, Zip};
 
-use utils::downloader::assure_file;
-use utils::error::Error;
+use utils::downloader::{Downloader, RemoteFile};
+use utils::error::{Context, Error};
 use utils::hdf5;
 use utils::hdf5::DynamicArray;
 
-use canonical::CanonicalData;
-use common::APP_INFO;
+use canonical::{CanonicalDataAs, CanonicalSamples, Real};
+use common::{data_dir, require_mirror};
+use dataset::{Dataset, Loader};
+use schema::{Column, HasSchema, Kind, Schema};
+
+/// Description of the data set.
+///
+/// mldata.org does not provide a separate description file, so it is kept here.
+const INFO: &str = "Auto MPG (mldata.org: uci-20070111-autompg)
+
+City-cycle fuel consumption in miles per gallon, taken from the StatLib library maintained at
+Carnegie Mellon University and used in the 1983 American Statistical Association Exposition.
+
+Number of instances: 398
+Number of attributes: 7 (cylinders, displacement, horsepower, weight, acceleration,
+                         model year, origin)
+Missing attribute values: horsepower has 6 missing values
+Target: fuel consumption in miles per gallon (mpg)
+";
+
+const DATA_FILE: RemoteFile = RemoteFile {
+    name: "uci-20070111-autompg.hdf5",
+    urls: &[],
+    sha256: None,
+    size: None,
+};
+
+/// All files of the data set
+pub const FILES: &[RemoteFile] = &[DATA_FILE];
+
+/// Directory of the data set, relative to the data home
+pub const SUBDIR: &str = "mldata.org";
 
 /// Configure the loader for the data set.
 ///
 /// This structure implements the builder pattern to configure the [`DataSetLoader`].
 pub struct DataSet {
-    data_root: path::PathBuf,
+    data_root: Option<path::PathBuf>,
     download: bool,
+    downloader: Downloader,
+}
+
+impl Default for DataSet {
+    fn default() -> Self {
+        DataSet::new()
+    }
 }
 
 impl DataSet {
     pub fn new() -> Self {
         DataSet {
-            data_root: get_app_dir(AppDataType::UserData, &APP_INFO, "mldata.org").unwrap(),
+            data_root: None,
             download: true,
+            downloader: Downloader::new(),
         }
     }
 
     pub fn create(&self) -> Result<DataSetLoader, Error> {
-        DataSetLoader::new(&self.data_root, self.download)
+        let data_root = match self.data_root {
+            Some(ref path) => path.clone(),
+            None => data_dir(SUBDIR)?,
+        };
+        DataSetLoader::with_downloader(&data_root, self.download, &self.downloader)
     }
 
     pub fn data_root<P: AsRef<path::Path>>(&mut self, p: P) -> &mut Self {
-        self.data_root = p.as_ref().into();
+        self.data_root = Some(p.as_ref().into());
         self
     }
 
@@ -43,6 +90,8 @@ impl DataSet {
         self.download = b;
         self
     }
+
+    downloader_options!();
 }
 
 /// Load the data set.
@@ -57,13 +106,19 @@ pub struct DataSetLoader {
 impl DataSetLoader {
     /// new
     pub fn new<P: AsRef<path::Path>>(data_path: P, download: bool) -> Result<DataSetLoader, Error> {
+        DataSetLoader::with_downloader(data_path, download, &Downloader::new())
+    }
+
+    /// Like `new`, but download missing files with the given `downloader`.
+    pub fn with_downloader<P: AsRef<path::Path>>(data_path: P, download: bool, downloader: &Downloader) -> Result<DataSetLoader, Error> {
         let data_path = data_path.as_ref();
         fs::create_dir_all(data_path)?;
 
-        let data_file = data_path.join("uci-20070111-autompg.hdf5");
+        let data_file = data_path.join(DATA_FILE.name);
 
         if download {
-            assure_file(&data_file, "http://mldata.org/repository/data/download/uci-20070111-autompg")?;
+            require_mirror(Data::NAME, &data_file, &DATA_FILE, downloader)?;
+            downloader.assure_files(&[(&data_file, &DATA_FILE)])?;
         }
 
         Ok(DataSetLoader{
@@ -71,25 +126,39 @@ impl DataSetLoader {
         })
     }
 
+    /// Check that all files of the data set are complete and unmodified.
+    pub fn verify(&self) -> Result<(), Error> {
+        DATA_FILE.verify(&self.data_file)?;
+        Ok(())
+    }
+
+    pub fn load_info(&self) -> Result<String, Error> {
+        Ok(INFO.to_owned())
+    }
+
     pub fn load_data(&self) -> Result<Data, Error> {
+        self.read_file().in_file(&self.data_file)
+    }
+
+    fn read_file(&self) -> Result<Data, Error> {
         let file = hdf5::File::open(&self.data_file)?;
 
         let int0 = if let DynamicArray::Int32(arr) = file.dataset("/data/int0")?.read()? {
             arr
         } else {
-            return Err(Error::Internal)
+            return Err(Error::DataType { path: "/data/int0".to_owned(), expected: "int32" })
         };
 
         let double1 = if let DynamicArray::Float64(arr) = file.dataset("/data/double1")?.read()? {
             arr
         } else {
-            return Err(Error::Internal)
+            return Err(Error::DataType { path: "/data/double1".to_owned(), expected: "float64" })
         };
 
         let int2 = if let DynamicArray::Int32(arr) = file.dataset("/data/int2")?.read()? {
             arr
         } else {
-            return Err(Error::Internal)
+            return Err(Error::DataType { path: "/data/int2".to_owned(), expected: "int32" })
         };
 
         let mut x = Vec::new();
@@ -107,7 +176,7 @@ impl DataSetLoader {
                     cylinders: i0[0],
                     displacement: i0[1],
                     horsepower: match i0[2] {
-                        -2147483648 => ::std::f64::NAN,
+                        -2147483648 => f64::NAN,
                         nr => nr as f64,
                     },
                     weight: i0[3],
@@ -124,6 +193,22 @@ impl DataSetLoader {
     }
 }
 
+impl Loader for DataSetLoader {
+    type Data = Data;
+
+    fn load_info(&self) -> Result<String, Error> {
+        DataSetLoader::load_info(self)
+    }
+
+    fn load_data(&self) -> Result<Data, Error> {
+        DataSetLoader::load_data(self)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        DataSetLoader::verify(self)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct FeatureRow {
     pub cylinders: i32,
@@ -166,28 +251,74 @@ impl Data {
     }
 }
 
-impl CanonicalData for Data {
-    fn to_canonical(&self) -> (Array2<f64>, Array2<f64>) {
-        let mut x_tmp = Vec::new();
-        for xi in self.x.iter() {
-            x_tmp.push(xi.cylinders as f64);
-            x_tmp.push(xi.displacement as f64);
-            x_tmp.push(xi.horsepower as f64);
-            x_tmp.push(xi.weight as f64);
-            x_tmp.push(xi.acceleration as f64);
-            x_tmp.push(xi.model_year as f64);
-            x_tmp.push(xi.origin as f64);
+impl Dataset for Data {
+    const NAME: &'static str = "mldata/uci-20070111-autompg";
+
+    type Features<'a> = &'a FeatureRow;
+    type Target = TargetVar;
+    type Loader = DataSetLoader;
+
+    fn loader() -> Result<DataSetLoader, Error> {
+        DataSet::new().create()
+    }
+
+    fn n_samples(&self) -> usize {
+        Data::n_samples(self)
+    }
+
+    fn get_sample(&self, idx: usize) -> (&FeatureRow, TargetVar) {
+        Data::get_sample(self, idx)
+    }
+}
+
+impl HasSchema for Data {
+    fn schema() -> Schema {
+        Schema {
+            features: vec![
+                Column::new("cylinders", "number of cylinders", Kind::Integer),
+                Column::new("displacement", "engine displacement", Kind::Continuous).unit("cu. in."),
+                Column::new("horsepower", "engine power", Kind::Continuous).unit("hp"),
+                Column::new("weight", "vehicle weight", Kind::Continuous).unit("lbs"),
+                Column::new("acceleration", "time to accelerate from 0 to 60 mph", Kind::Continuous).unit("s"),
+                Column::new("model_year", "model year (last two digits)", Kind::Integer),
+                Column::new("origin", "region of origin", Kind::categorical_from(1, &["USA", "Europe", "Japan"])),
+            ],
+            targets: vec![
+                Column::new("mpg", "fuel consumption", Kind::Continuous).unit("miles per gallon"),
+            ],
         }
-        let y_tmp = self.y.iter().map(|yi| yi.mpg as f64).collect();
-        let x = Array2::from_shape_vec((self.n_samples, 7), x_tmp).unwrap();
-        let y = Array2::from_shape_vec((self.n_samples, 1), y_tmp).unwrap();
-        (x, y)
+    }
+}
+
+impl<T: Real> CanonicalDataAs<T> for Data {
+    fn to_canonical_as(&self) -> (Array2<T>, Array2<T>) {
+        let idx: Vec<_> = (0..self.n_samples).collect();
+        self.select_canonical(&idx)
+    }
+}
+
+impl<T: Real> CanonicalSamples<T> for Data {
+    fn canonical_shape(&self) -> (usize, usize, usize) {
+        (self.n_samples, 7, 1)
+    }
+
+    fn write_canonical(&self, idx: usize, mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>) {
+        let (xi, yi) = self.get_sample(idx);
+        x[0] = T::from_i32(xi.cylinders);
+        x[1] = T::from_i32(xi.displacement);
+        x[2] = T::from_f64(xi.horsepower);
+        x[3] = T::from_i32(xi.weight);
+        x[4] = T::from_f64(xi.acceleration);
+        x[5] = T::from_i32(xi.model_year);
+        x[6] = T::from_i32(xi.origin);
+        y[0] = T::from_i32(yi.mpg);
     }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use canonical::CanonicalData;
     use super::*;
 
     #[test]
@@ -1,40 +1,86 @@
 //! The "Boston Housing" data set from mldata.org (regression-datasets housing).
+//!
+//! mldata.org is no longer online and no other public copy of its HDF5 files is known. To download
+//! the data, configure a mirror that provides `regression-datasets-housing.hdf5`, either with
+//! [`DataSet::mirror`](struct.DataSet.html#method.mirror) or the `MLDATA_MIRRORS` environment
+//! variable. Otherwise, loading fails with `Error::NoMirror` unless the file is already in the
+//! data directory.
 
 use std::fs;
 use std::path;
 
-use app_dirs::*;
-use ndarray::{Array2, Zip};
+use ndarray::{Array2, ArrayViewMut1, Zip};
 
-use utils::downloader::assure_file;
-use utils::error::Error;
+use utils::downloader::{Downloader, RemoteFile};
+use utils::error::{Context, Error};
 use utils::hdf5;
 
-use canonical::CanonicalData;
-use common::APP_INFO;
+use canonical::{CanonicalDataAs, CanonicalSamples, Real};
+use common::{data_dir, require_mirror};
+use dataset::{Dataset, Loader};
+use schema::{Column, HasSchema, Kind, Schema};
+
+/// Description of the data set.
+///
+/// mldata.org does not provide a separate description file, so it is kept here.
+const INFO: &str = "Boston Housing (mldata.org: regression-datasets-housing)
+
+Housing values in suburbs of Boston, originally published by Harrison, D. and Rubinfeld, D.L.
+'Hedonic prices and the demand for clean air', J. Environ. Economics & Management, vol.5,
+81-102, 1978.
+
+Number of instances: 506
+Number of attributes: 13 continuous and integer attributes, 1 binary attribute (chas)
+Target: median value of owner-occupied homes in $1000's (medv)
+";
+
+const DATA_FILE: RemoteFile = RemoteFile {
+    name: "regression-datasets-housing.hdf5",
+    urls: &[],
+    sha256: None,
+    size: None,
+};
+
+/// All files of the data set
+pub const FILES: &[RemoteFile] = &[DATA_FILE];
+
+/// Directory of the data set, relative to the data home
+pub const SUBDIR: &str = "mldata.org";
 
 /// Configure the loader for the data set.
 ///
 /// This structure implements the builder pattern to configure the [`DataSetLoader`].
 pub struct DataSet {
-    data_root: path::PathBuf,
+    data_root: Option<path::PathBuf>,
     download: bool,
+    downloader: Downloader,
+}
+
+impl Default for DataSet {
+    fn default() -> Self {
+        DataSet::new()
+    }
 }
 
 impl DataSet {
     pub fn new() -> Self {
         DataSet {
-            data_root: get_app_dir(AppDataType::UserData, &APP_INFO, "mldata.org").unwrap(),
+            data_root: None,
             download: true,
+            downloader: Downloader::new(),
         }
     }
 
     pub fn create(&self) -> Result<DataSetLoader, Error> {
-        DataSetLoader::new(&self.data_root, self.download)
+        let data_root = match self.data_root {
+            Some(ref path) => path.clone(),
+            None => data_dir(SUBDIR)?,
+        };
+        DataSetLoader::with_downloader(&data_root, self.download, &self.downloader)
     }
 
     pub fn data_root<P: AsRef<path::Path>>(&mut self, p: P) -> &mut Self {
-        self.data_root = p.as_ref().into();
+        self.data_root = Some(p.as_ref().into());
         self
     }
 
@@ -42,6 +88,8 @@ impl DataSet {
         self.download = b;
         self
     }
+
+    downloader_options!();
 }
 
 /// Load the data set.
@@ -56,13 +104,19 @@ pub struct DataSetLoader {
 impl DataSetLoader {
     /// new
     pub fn new<P: AsRef<path::Path>>(data_path: P, download: bool) -> Result<DataSetLoader, Error> {
+        DataSetLoader::with_downloader(data_path, download, &Downloader::new())
+    }
+
+    /// Like `new`, but download missing files with the given `downloader`.
+    pub fn with_downloader<P: AsRef<path::Path>>(data_path: P, download: bool, downloader: &Downloader) -> Result<DataSetLoader, Error> {
         let data_path = data_path.as_ref();
         fs::create_dir_all(data_path)?;
 
-        let data_file = data_path.join("regression-datasets-housing.hdf5");
+        let data_file = data_path.join(DATA_FILE.name);
 
         if download {
-            assure_file(&data_file, "http://mldata.org/repository/data/download/regression-datasets-housing")?;
+            require_mirror(Data::NAME, &data_file, &DATA_FILE, downloader)?;
+            downloader.assure_files(&[(&data_file, &DATA_FILE)])?;
         }
 
         Ok(DataSetLoader{
@@ -70,7 +124,21 @@ impl DataSetLoader {
         })
     }
 
+    /// Check that all files of the data set are complete and unmodified.
+    pub fn verify(&self) -> Result<(), Error> {
+        DATA_FILE.verify(&self.data_file)?;
+        Ok(())
+    }
+
+    pub fn load_info(&self) -> Result<String, Error> {
+        Ok(INFO.to_owned())
+    }
+
     pub fn load_data(&self) -> Result<Data, Error> {
+        self.read_file().in_file(&self.data_file)
+    }
+
+    fn read_file(&self) -> Result<Data, Error> {
         let file = hdf5::File::open(&self.data_file)?;
 
         let double0 = file.dataset("/data/double0")?.read_f64()?;
@@ -124,6 +192,22 @@ impl DataSetLoader {
     }
 }
 
+impl Loader for DataSetLoader {
+    type Data = Data;
+
+    fn load_info(&self) -> Result<String, Error> {
+        DataSetLoader::load_info(self)
+    }
+
+    fn load_data(&self) -> Result<Data, Error> {
+        DataSetLoader::load_data(self)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        DataSetLoader::verify(self)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct FeatureRow {
     /// per capita crime rate by town
@@ -198,36 +282,107 @@ impl Data {
     }
 }
 
-impl CanonicalData for Data {
-    fn to_canonical(&self) -> (Array2<f64>, Array2<f64>) {
-        let mut x_tmp = Vec::new();
-        for xi in self.x.iter() {
-            x_tmp.push(xi.crim as f64);
-            x_tmp.push(xi.zn as f64);
-            x_tmp.push(xi.indus as f64);
-            x_tmp.push(if xi.chas {1.0} else {0.0});
-            x_tmp.push(xi.nox as f64);
-            x_tmp.push(xi.rm as f64);
-            x_tmp.push(xi.age as f64);
-            x_tmp.push(xi.dis as f64);
-            x_tmp.push(xi.rad as f64);
-            x_tmp.push(xi.tax as f64);
-            x_tmp.push(xi.ptratio as f64);
-            x_tmp.push(xi.b as f64);
-            x_tmp.push(xi.lstat as f64);
+impl Dataset for Data {
+    const NAME: &'static str = "mldata/regression-datasets-housing";
+
+    type Features<'a> = &'a FeatureRow;
+    type Target = TargetVar;
+    type Loader = DataSetLoader;
+
+    fn loader() -> Result<DataSetLoader, Error> {
+        DataSet::new().create()
+    }
+
+    fn n_samples(&self) -> usize {
+        Data::n_samples(self)
+    }
+
+    fn get_sample(&self, idx: usize) -> (&FeatureRow, TargetVar) {
+        Data::get_sample(self, idx)
+    }
+}
+
+impl HasSchema for Data {
+    fn schema() -> Schema {
+        Schema {
+            features: vec![
+                Column::new("crim", "per capita crime rate by town", Kind::Continuous),
+                Column::new("zn", "proportion of residential land zoned for lots over 25,000 sq.ft.", Kind::Continuous).unit("%"),
+                Column::new("indus", "proportion of non-retail business acres per town", Kind::Continuous).unit("%"),
+                Column::new("chas", "tract bounds Charles River", Kind::Boolean),
+                Column::new("nox", "nitric oxides concentration", Kind::Continuous).unit("parts per 10 million"),
+                Column::new("rm", "average number of rooms per dwelling", Kind::Continuous),
+                Column::new("age", "proportion of owner-occupied units built prior to 1940", Kind::Continuous).unit("%"),
+                Column::new("dis", "weighted distances to five Boston employment centres", Kind::Continuous),
+                Column::new("rad", "index of accessibility to radial highways", Kind::Integer),
+                Column::new("tax", "full-value property-tax rate", Kind::Continuous).unit("$ per $10,000"),
+                Column::new("ptratio", "pupil-teacher ratio by town", Kind::Continuous),
+                Column::new("b", "1000(Bk - 0.63)^2 where Bk is the proportion of blacks by town", Kind::Continuous),
+                Column::new("lstat", "lower status of the population", Kind::Continuous).unit("%"),
+            ],
+            targets: vec![
+                Column::new("medv", "median value of owner-occupied homes", Kind::Continuous).unit("$1000"),
+            ],
         }
-        let y_tmp = self.y.iter().map(|yi| yi.medv).collect();
-        let x = Array2::from_shape_vec((self.n_samples, 13), x_tmp).unwrap();
-        let y = Array2::from_shape_vec((self.n_samples, 1), y_tmp).unwrap();
-        (x, y)
+    }
+}
+
+impl<T: Real> CanonicalDataAs<T> for Data {
+    fn to_canonical_as(&self) -> (Array2<T>, Array2<T>) {
+        let idx: Vec<_> = (0..self.n_samples).collect();
+        self.select_canonical(&idx)
+    }
+}
+
+impl<T: Real> CanonicalSamples<T> for Data {
+    fn canonical_shape(&self) -> (usize, usize, usize) {
+        (self.n_samples, 13, 1)
+    }
+
+    fn write_canonical(&self, idx: usize, mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>) {
+        let (xi, yi) = self.get_sample(idx);
+        x[0] = T::from_f64(xi.crim);
+        x[1] = T::from_i32(xi.zn);
+        x[2] = T::from_f64(xi.indus);
+        x[3] = T::from(xi.chas as u8);
+        x[4] = T::from_f64(xi.nox);
+        x[5] = T::from_f64(xi.rm);
+        x[6] = T::from_f64(xi.age);
+        x[7] = T::from_f64(xi.dis);
+        x[8] = T::from_i32(xi.rad);
+        x[9] = T::from_i32(xi.tax);
+        x[10] = T::from_i32(xi.ptratio);
+        x[11] = T::from_f64(xi.b);
+        x[12] = T::from_f64(xi.lstat);
+        y[0] = T::from_f64(yi.medv);
     }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use canonical::CanonicalData;
+    use utils::temp::TempDir;
     use super::*;
 
+    #[test]
+    fn no_mirror() {
+        let dir = TempDir::new("boston");
+        let mut downloader = Downloader::new();
+        if downloader.urls(&DATA_FILE).is_empty() {
+            match DataSetLoader::with_downloader(dir.path(), true, &downloader) {
+                Err(Error::NoMirror { data_set, file }) => {
+                    assert_eq!((data_set, file), ("mldata/regression-datasets-housing", DATA_FILE.name))
+                }
+                _ => panic!("expected missing mirror error"),
+            }
+        }
+
+        // a cached file does not need a mirror
+        fs::write(dir.join(DATA_FILE.name), b"cached").unwrap();
+        assert!(DataSetLoader::with_downloader(dir.path(), true, &*downloader.offline(true)).is_ok());
+    }
+
     #[test]
     fn load() {
         let data = DataSet::new().download(true).create().unwrap();
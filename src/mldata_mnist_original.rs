@@ -1,41 +1,91 @@
 //! The "MNIST" database of handwritten digits from mldata.org.
+//!
+//! mldata.org is no longer online and no other public copy of its HDF5 files is known. To download
+//! the data, configure a mirror that provides `mnist-original.hdf5`, either with
+//! [`DataSet::mirror`](struct.DataSet.html#method.mirror) or the `MLDATA_MIRRORS` environment
+//! variable. Otherwise, loading fails with `Error::NoMirror` unless the file is already in the
+//! data directory.
 
 use std::fs;
+use std::ops::Range;
 use std::path;
 
-use app_dirs::*;
-use ndarray::{Array1, Array2, Array3, ArrayView2, Axis, Zip};
+use ndarray::{Array1, Array2, ArrayView2, ArrayViewMut1, Axis, Ix2, Zip};
 
-use utils::downloader::assure_file;
-use utils::error::Error;
+use utils::downloader::{Downloader, RemoteFile};
+use utils::error::{Context, Error};
 use utils::hdf5;
 use utils::hdf5::DynamicArray;
 
-use canonical::CanonicalData;
-use common::APP_INFO;
+use canonical::{CanonicalDataAs, CanonicalSamples, CanonicalView};
+use common::{data_dir, require_mirror};
+use dataset::{Dataset, Loader, Split};
+use schema::{pixel_columns, Column, HasSchema, Kind, Schema};
+
+/// Description of the data set.
+///
+/// mldata.org does not provide a separate description file, so it is kept here.
+const INFO: &str = "MNIST (mldata.org: mnist-original)
+
+The MNIST database of handwritten digits by Yann LeCun, Corinna Cortes and Christopher J.C.
+Burges. The digits have been size-normalized and centered in a fixed-size 28x28 image.
+
+Number of instances: 70000 (the first 60000 are the original training set, the remaining 10000
+                     the original test set)
+Number of attributes: 784 pixel intensities in the range 0..255
+Target: digit class 0..9
+";
+
+/// Number of samples in the official training set. The remaining samples form the testing set.
+const N_TRAIN: usize = 60000;
+
+const DATA_FILE: RemoteFile = RemoteFile {
+    name: "mnist-original.hdf5",
+    urls: &[],
+    sha256: None,
+    size: None,
+};
+
+/// All files of the data set
+pub const FILES: &[RemoteFile] = &[DATA_FILE];
+
+/// Directory of the data set, relative to the data home
+pub const SUBDIR: &str = "mldata.org";
 
 /// Configure the loader for the data set.
 ///
 /// This structure implements the builder pattern to configure the [`DataSetLoader`].
 pub struct DataSet {
-    data_root: path::PathBuf,
+    data_root: Option<path::PathBuf>,
     download: bool,
+    downloader: Downloader,
+}
+
+impl Default for DataSet {
+    fn default() -> Self {
+        DataSet::new()
+    }
 }
 
 impl DataSet {
     pub fn new() -> Self {
         DataSet {
-            data_root: get_app_dir(AppDataType::UserData, &APP_INFO, "mldata.org").unwrap(),
+            data_root: None,
             download: true,
+            downloader: Downloader::new(),
         }
     }
 
     pub fn create(&self) -> Result<DataSetLoader, Error> {
-        DataSetLoader::new(&self.data_root, self.download)
+        let data_root = match self.data_root {
+            Some(ref path) => path.clone(),
+            None => data_dir(SUBDIR)?,
+        };
+        DataSetLoader::with_downloader(&data_root, self.download, &self.downloader)
     }
 
     pub fn data_root<P: AsRef<path::Path>>(&mut self, p: P) -> &mut Self {
-        self.data_root = p.as_ref().into();
+        self.data_root = Some(p.as_ref().into());
         self
     }
 
@@ -43,6 +93,8 @@ impl DataSet {
         self.download = b;
         self
     }
+
+    downloader_options!();
 }
 
 /// Load the data set.
@@ -57,13 +109,19 @@ pub struct DataSetLoader {
 impl DataSetLoader {
     /// new
     pub fn new<P: AsRef<path::Path>>(data_path: P, download: bool) -> Result<DataSetLoader, Error> {
+        DataSetLoader::with_downloader(data_path, download, &Downloader::new())
+    }
+
+    /// Like `new`, but download missing files with the given `downloader`.
+    pub fn with_downloader<P: AsRef<path::Path>>(data_path: P, download: bool, downloader: &Downloader) -> Result<DataSetLoader, Error> {
         let data_path = data_path.as_ref();
         fs::create_dir_all(data_path)?;
 
-        let data_file = data_path.join("mnist-original.hdf5");
+        let data_file = data_path.join(DATA_FILE.name);
 
         if download {
-            assure_file(&data_file, "http://mldata.org/repository/data/download/mnist-original")?;
+            require_mirror(Data::NAME, &data_file, &DATA_FILE, downloader)?;
+            downloader.assure_files(&[(&data_file, &DATA_FILE)])?;
         }
 
         Ok(DataSetLoader{
@@ -71,38 +129,121 @@ impl DataSetLoader {
         })
     }
 
+    /// Check that all files of the data set are complete and unmodified.
+    pub fn verify(&self) -> Result<(), Error> {
+        DATA_FILE.verify(&self.data_file)?;
+        Ok(())
+    }
+
+    pub fn load_info(&self) -> Result<String, Error> {
+        Ok(INFO.to_owned())
+    }
+
     pub fn load_data(&self) -> Result<Data, Error> {
+        self.read_file(Split::All).in_file(&self.data_file)
+    }
+
+    /// Read the samples of a split, without reading the others.
+    fn read_file(&self, split: Split) -> Result<Data, Error> {
         let file = hdf5::File::open(&self.data_file)?;
 
-        let data = if let DynamicArray::UInt8(arr) = file.dataset("/data/data")?.read()? {
+        // one column per image, so the samples of a split are a range of columns
+        let data = file.dataset("/data/data")?;
+        let n_samples = data.get_space().shape()?.last().cloned().unwrap_or(0);
+        let samples = match split {
+            Split::All => 0..n_samples,
+            Split::Train => 0..N_TRAIN.min(n_samples),
+            Split::Test => N_TRAIN.min(n_samples)..n_samples,
+            Split::Validation => return Err(Error::NoOfficialSplit),
+        };
+
+        let data = if let DynamicArray::UInt8(arr) = read_columns(&data, &samples)? {
             arr
         } else {
-            return Err(Error::DataType)
+            return Err(Error::DataType { path: "/data/data".to_owned(), expected: "uint8" })
         };
 
-        let label = if let DynamicArray::Float64(arr) = file.dataset("/data/label")?.read()? {
+        let label = if let DynamicArray::Float64(arr) = read_columns(&file.dataset("/data/label")?, &samples)? {
             arr
         } else {
-            return Err(Error::DataType)
+            return Err(Error::DataType { path: "/data/label".to_owned(), expected: "float64" })
         };
 
-        let x: Array3<u8> = data.t().into_shape((70000, 28, 28))?.to_owned();
+        // one column per image; the pixels of each image are stored row by row
+        let x: Array2<u8> = data.into_dimensionality::<Ix2>()?;
 
         let y: Array1<_> = label.iter().map(|&f| f as u8).collect();
 
         Ok(Data::from(x, y))
     }
+
+    /// Load the official training or testing set, or both combined.
+    ///
+    /// The first 60000 samples are the official training set, the remaining 10000 samples the
+    /// official testing set.
+    pub fn load_split(&self, split: Split) -> Result<Data, Error> {
+        self.read_file(split).in_file(&self.data_file)
+    }
+
+    pub fn load_training_data(&self) -> Result<Data, Error> {
+        self.load_split(Split::Train)
+    }
+
+    pub fn load_testing_data(&self) -> Result<Data, Error> {
+        self.load_split(Split::Test)
+    }
+}
+
+/// Read a range of entries along the last dimension of a dataset.
+fn read_columns(dataset: &hdf5::Dataset, columns: &Range<usize>) -> Result<DynamicArray, Error> {
+    let mut count = dataset.get_space().shape()?;
+    if count.is_empty() {
+        return Ok(dataset.read()?)
+    }
+    let last = count.len() - 1;
+    let mut start = vec![0; count.len()];
+    start[last] = columns.start;
+    count[last] = columns.len();
+    let step = vec![1; count.len()];
+    Ok(dataset.read_selection(&hdf5::Selection::Hyperslab { start, step, count })?)
+}
+
+impl Loader for DataSetLoader {
+    type Data = Data;
+
+    fn load_info(&self) -> Result<String, Error> {
+        DataSetLoader::load_info(self)
+    }
+
+    fn load_data(&self) -> Result<Data, Error> {
+        DataSetLoader::load_data(self)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        DataSetLoader::verify(self)
+    }
+
+    fn has_official_split(&self) -> bool {
+        true
+    }
+
+    fn load_split(&self, split: Split) -> Result<Data, Error> {
+        DataSetLoader::load_split(self, split)
+    }
 }
 
 /// In-memory representation of the data
+///
+/// The images are stored in the same layout as in the data file: a 784 x n_samples array, where
+/// each column contains the pixels of one image.
 pub struct Data {
-    x: Array3<u8>,
+    x: Array2<u8>,
     y: Array1<u8>,
 }
 
 impl Data {
-    fn from(x: Array3<u8>, y: Array1<u8>) -> Self {
-        assert_eq!(x.len(), y.len() * 784);
+    fn from(x: Array2<u8>, y: Array1<u8>) -> Self {
+        assert_eq!(x.shape(), [784, y.len()]);
         Data {
             x,
             y,
@@ -114,37 +255,81 @@ impl Data {
     }
 
     pub fn get_sample(&self, idx: usize) -> (ArrayView2<u8>, u8) {
-        let xv = self.x.subview(Axis(0), idx).reversed_axes();
-        (xv, self.y[idx])
+        let images = self.x.view().into_shape((28, 28, self.y.len())).unwrap();
+        (images.into_subview(Axis(2), idx), self.y[idx])
     }
 }
 
-impl CanonicalData for Data {
-    fn to_canonical(&self) -> (Array2<f64>, Array2<f64>) {
-        let mut x = Array3::zeros((self.y.len(), 28, 28));
-        let mut y = Array2::zeros((self.y.len(), 1));
+impl Dataset for Data {
+    const NAME: &'static str = "mldata/mnist-original";
 
-        Zip::from(&self.x)
-            .and(&mut x)
-            .apply(|xi, xf| *xf = *xi as f64);
+    type Features<'a> = ArrayView2<'a, u8>;
+    type Target = u8;
+    type Loader = DataSetLoader;
 
-        for (yi, yf) in self.y.iter().zip(y.iter_mut()) {
-            *yf = *yi as f64
-        }
+    fn loader() -> Result<DataSetLoader, Error> {
+        DataSet::new().create()
+    }
+
+    fn n_samples(&self) -> usize {
+        Data::n_samples(self)
+    }
 
-        let mut tmp = Array2::zeros((28, 28));
+    fn get_sample(&self, idx: usize) -> (ArrayView2<u8>, u8) {
+        Data::get_sample(self, idx)
+    }
+}
 
-        for mut img in x.outer_iter_mut() {
-            tmp.assign(&img.t());
-            img.assign(&tmp);
+impl HasSchema for Data {
+    fn schema() -> Schema {
+        Schema {
+            features: pixel_columns(28, 28, 255),
+            targets: vec![
+                Column::new("digit", "handwritten digit",
+                            Kind::categorical(&["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"])),
+            ],
         }
-        (x.into_shape((self.y.len(), 784)).unwrap(), y)
+    }
+}
+
+impl<T: Copy + Default + From<u8>> CanonicalDataAs<T> for Data {
+    fn to_canonical_as(&self) -> (Array2<T>, Array2<T>) {
+        let (x8, y8) = self.canonical_view();
+
+        let mut x = Array2::from_elem((self.y.len(), 784), T::default());
+        let mut y = Array2::from_elem((self.y.len(), 1), T::default());
+
+        Zip::from(&mut x).and(&x8).apply(|out, &inp| *out = T::from(inp));
+        Zip::from(&mut y).and(&y8).apply(|out, &inp| *out = T::from(inp));
+
+        (x, y)
+    }
+}
+
+impl<T: Copy + Default + From<u8>> CanonicalSamples<T> for Data {
+    fn canonical_shape(&self) -> (usize, usize, usize) {
+        (self.y.len(), 784, 1)
+    }
+
+    fn write_canonical(&self, idx: usize, mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>) {
+        Zip::from(&mut x).and(self.x.column(idx)).apply(|out, &inp| *out = T::from(inp));
+        y[0] = T::from(self.y[idx]);
+    }
+}
+
+impl CanonicalView for Data {
+    type Elem = u8;
+
+    fn canonical_view(&self) -> (ArrayView2<u8>, ArrayView2<u8>) {
+        let y = self.y.view().into_shape((self.y.len(), 1)).unwrap();
+        (self.x.t(), y)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use ndarray::Array2;
+    use ndarray::{Array2, ArrayView1};
+    use canonical::CanonicalData;
     use super::*;
 
     #[test]
@@ -161,6 +346,21 @@ mod tests {
         assert_eq!(tst.get_sample(30000).1, 4);
     }
 
+    #[test]
+    fn split() {
+        let data = DataSet::new().download(true).create().unwrap();
+        let all = data.load_data().unwrap();
+
+        let train = data.load_split(Split::Train).unwrap();
+        assert_eq!(train.n_samples(), 60000);
+        assert_eq!(train.get_sample(4150), all.get_sample(4150));
+
+        let test = data.load_split(Split::Test).unwrap();
+        assert_eq!(test.n_samples(), 10000);
+        assert_eq!(test.get_sample(0), all.get_sample(60000));
+        assert_eq!(test.get_sample(9999), all.get_sample(69999));
+    }
+
     #[test]
     fn canonical() {
         let data = DataSet::new().download(true).create().unwrap();
@@ -175,6 +375,74 @@ mod tests {
         assert_eq!(y[(30000, 0)], 4.0);
     }
 
+    #[test]
+    fn canonical_native() {
+        let data = DataSet::new().download(true).create().unwrap();
+
+        let (x, y): (Array2<u8>, Array2<u8>) = data.load_data().unwrap().into_canonical_as();
+        assert_eq!(x.subview(Axis(0), 4150), Array1::from_shape_vec(784, X_4150.to_vec()).unwrap());
+        assert_eq!(y[(30000, 0)], 4);
+
+        let (x, _): (Array2<f32>, Array2<f32>) = data.load_data().unwrap().into_canonical_as();
+        assert_eq!(x[(4150, 5 * 28 + 15)], 255.0);
+    }
+
+    #[test]
+    fn layout() {
+        let x = Array2::from_shape_fn((784, 3), |(p, i)| ((p + i) % 256) as u8);
+        let data = Data::from(x, Array1::from_vec(vec![7, 8, 9]));
+
+        let (img, label) = data.get_sample(1);
+        assert_eq!(label, 8);
+        assert_eq!(img[(0, 5)], 6);
+        assert_eq!(img[(2, 3)], (2 * 28 + 3 + 1) as u8);
+
+        let (xv, yv) = data.canonical_view();
+        let (x, y): (Array2<u8>, Array2<u8>) = data.to_canonical_as();
+        assert_eq!(xv, x);
+        assert_eq!(yv, y);
+        assert_eq!(x.row(1), img.iter().cloned().collect::<Array1<u8>>());
+
+        let (xb, yb): (Array2<u8>, Array2<u8>) = data.select_canonical(&[2, 1]);
+        assert_eq!(xb.row(1), x.row(1));
+        assert_eq!(yb, Array2::from_shape_vec((2, 1), vec![9, 8]).unwrap());
+    }
+
+    #[test]
+    fn columns() {
+        use ndarray::arr2;
+        use utils::hdf5::writer::*;
+
+        let mut w = Writer::new();
+        let pixels: Vec<u8> = (0..15).collect();
+        let data = w.dataset(&[3, 5], integer(1, false, false), &pixels, Storage::Contiguous);
+        let labels = bytes(&[0.0f64, 1.0, 2.0, 3.0, 4.0], 8, f64::to_bits);
+        let label = w.dataset(&[1, 5], float(8, false), &labels, Storage::Chunked(&[1, 2], &[]));
+        let root = w.group(&[("data", Member::Hard(data)), ("label", Member::Hard(label))]);
+        let path = save("mnist-columns", &w.finish(root));
+        let file = hdf5::File::open(&path).unwrap();
+
+        match read_columns(&file.dataset("data").unwrap(), &(1..4)).unwrap() {
+            DynamicArray::UInt8(a) => assert_eq!(a.into_raw_vec(), [1, 2, 3, 6, 7, 8, 11, 12, 13]),
+            a => panic!("unexpected array {:?}", a),
+        }
+        match read_columns(&file.dataset("label").unwrap(), &(3..5)).unwrap() {
+            DynamicArray::Float64(a) => assert_eq!(a, arr2(&[[3.0, 4.0]]).into_dyn()),
+            a => panic!("unexpected array {:?}", a),
+        }
+    }
+
+    #[test]
+    fn view() {
+        let data = DataSet::new().download(true).create().unwrap().load_data().unwrap();
+
+        let (x, y) = data.canonical_view();
+        assert_eq!(x.shape(), [70000, 28 * 28]);
+        assert_eq!(y.shape(), [70000, 1]);
+        assert_eq!(x.subview(Axis(0), 4150), ArrayView1::from(&X_4150[..]));
+        assert_eq!(y[(30000, 0)], 4);
+    }
+
     const X_4150: [u8; 784] = [
         0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,
         0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,
@@ -0,0 +1,204 @@
+//! Look up data sets by name
+//!
+//! Every bundled data set is registered under a stable name, such as `"uci/iris"` or
+//! `"mldata/mnist-original"`. This allows to select data sets at runtime, e.g. from a
+//! configuration file, without compile-time knowledge of the data set modules.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use mldata::registry;
+//!
+//! for entry in registry::entries() {
+//!     println!("{:24} {}", entry.name, entry.title);
+//! }
+//!
+//! let (data, entry) = registry::load("uci/iris").unwrap();
+//! let (x, y) = data.to_canonical();
+//! println!("{}: X {:?}, Y {:?}", entry.title, x.shape(), y.shape());
+//! ```
+
+use canonical::CanonicalData;
+use dataset::Dataset;
+use schema::{HasSchema, Schema};
+use utils::downloader::RemoteFile;
+use utils::error::Error;
+
+use mldata_auto_mpg;
+use mldata_boston;
+use mldata_mnist_original;
+use uci_auto_mpg;
+use uci_iris;
+use uci_optdigits;
+
+/// The kind of learning task a data set is intended for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Task {
+    Classification,
+    Regression,
+}
+
+/// Metadata of a registered data set.
+#[derive(Debug)]
+pub struct Entry {
+    /// Stable name of the data set
+    pub name: &'static str,
+
+    /// Human readable title
+    pub title: &'static str,
+
+    /// Where the data set comes from
+    pub homepage: &'static str,
+
+    /// The learning task
+    pub task: Task,
+
+    /// Directory of the data set, relative to the data home
+    pub directory: &'static str,
+
+    /// Files of the data set in its directory
+    pub files: &'static [RemoteFile],
+
+    loader: fn() -> Result<Box<dyn CanonicalData>, Error>,
+    schema: fn() -> Schema,
+}
+
+impl Entry {
+    /// Load the data set with default settings.
+    ///
+    /// This will download the data when run for the first time.
+    pub fn load(&self) -> Result<Box<dyn CanonicalData>, Error> {
+        (self.loader)()
+    }
+
+    /// Description of the columns in canonical representation.
+    pub fn schema(&self) -> Schema {
+        (self.schema)()
+    }
+}
+
+fn load_boxed<D: Dataset + CanonicalData + 'static>() -> Result<Box<dyn CanonicalData>, Error> {
+    Ok(Box::new(D::load()?))
+}
+
+static ENTRIES: [Entry; 6] = [
+    Entry {
+        name: <uci_auto_mpg::Data as Dataset>::NAME,
+        title: "Auto MPG",
+        homepage: "http://archive.ics.uci.edu/ml/datasets/Auto+MPG",
+        task: Task::Regression,
+        directory: uci_auto_mpg::SUBDIR,
+        files: uci_auto_mpg::FILES,
+        loader: load_boxed::<uci_auto_mpg::Data>,
+        schema: <uci_auto_mpg::Data as HasSchema>::schema,
+    },
+    Entry {
+        name: <uci_iris::Data as Dataset>::NAME,
+        title: "Iris",
+        homepage: "https://archive.ics.uci.edu/ml/datasets/iris",
+        task: Task::Classification,
+        directory: uci_iris::SUBDIR,
+        files: uci_iris::FILES,
+        loader: load_boxed::<uci_iris::Data>,
+        schema: <uci_iris::Data as HasSchema>::schema,
+    },
+    Entry {
+        name: <uci_optdigits::Data as Dataset>::NAME,
+        title: "Optical Recognition of Handwritten Digits",
+        homepage: "https://archive.ics.uci.edu/ml/datasets/Optical+Recognition+of+Handwritten+Digits",
+        task: Task::Classification,
+        directory: uci_optdigits::SUBDIR,
+        files: uci_optdigits::FILES,
+        loader: load_boxed::<uci_optdigits::Data>,
+        schema: <uci_optdigits::Data as HasSchema>::schema,
+    },
+    Entry {
+        name: <mldata_auto_mpg::Data as Dataset>::NAME,
+        title: "Auto MPG",
+        homepage: "http://mldata.org/repository/data/viewslug/uci-20070111-autompg/",
+        task: Task::Regression,
+        directory: mldata_auto_mpg::SUBDIR,
+        files: mldata_auto_mpg::FILES,
+        loader: load_boxed::<mldata_auto_mpg::Data>,
+        schema: <mldata_auto_mpg::Data as HasSchema>::schema,
+    },
+    Entry {
+        name: <mldata_boston::Data as Dataset>::NAME,
+        title: "Boston Housing",
+        homepage: "http://mldata.org/repository/data/viewslug/regression-datasets-housing/",
+        task: Task::Regression,
+        directory: mldata_boston::SUBDIR,
+        files: mldata_boston::FILES,
+        loader: load_boxed::<mldata_boston::Data>,
+        schema: <mldata_boston::Data as HasSchema>::schema,
+    },
+    Entry {
+        name: <mldata_mnist_original::Data as Dataset>::NAME,
+        title: "MNIST",
+        homepage: "http://mldata.org/repository/data/viewslug/mnist-original/",
+        task: Task::Classification,
+        directory: mldata_mnist_original::SUBDIR,
+        files: mldata_mnist_original::FILES,
+        loader: load_boxed::<mldata_mnist_original::Data>,
+        schema: <mldata_mnist_original::Data as HasSchema>::schema,
+    },
+];
+
+/// All registered data sets.
+pub fn entries() -> &'static [Entry] {
+    &ENTRIES
+}
+
+/// Find a data set by name.
+pub fn find(name: &str) -> Result<&'static Entry, Error> {
+    ENTRIES.iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| Error::UnknownDataSet(name.to_owned()))
+}
+
+/// Load a data set by name with default settings.
+///
+/// Returns the data together with its metadata.
+pub fn load(name: &str) -> Result<(Box<dyn CanonicalData>, &'static Entry), Error> {
+    let entry = find(name)?;
+    Ok((entry.load()?, entry))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        for entry in entries() {
+            assert_eq!(find(entry.name).unwrap().name, entry.name);
+            assert_eq!(entries().iter().filter(|e| e.name == entry.name).count(), 1);
+        }
+
+        match find("uci/no-such-data") {
+            Err(Error::UnknownDataSet(ref name)) => assert_eq!(name, "uci/no-such-data"),
+            _ => panic!("expected unknown data set error"),
+        }
+    }
+
+    #[test]
+    fn declared_digests() {
+        for file in entries().iter().flat_map(|entry| entry.files) {
+            let digest = file.sha256.unwrap_or_else(|| panic!("no digest declared for {}", file.name));
+            assert!(digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()),
+                    "malformed digest of {}", file.name);
+            assert!(file.size.unwrap_or(0) > 0, "no size declared for {}", file.name);
+        }
+    }
+
+    #[test]
+    fn load_by_name() {
+        let (data, entry) = load("uci/iris").unwrap();
+        assert_eq!(entry.task, Task::Classification);
+
+        let (x, y) = data.to_canonical();
+        assert_eq!(x.shape(), [150, 4]);
+        assert_eq!(y.shape(), [150, 1]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Description of the columns in canonical representation
+//!
+//! A [`Schema`](struct.Schema.html) describes each column of the features (X) and target
+//! variables (Y) in canonical representation: its name, what it means and what kind of values it
+//! holds. The columns are listed in the same order as they appear in the canonical
+//! representation.
+//!
+//! # Examples
+//!
+//! ```
+//! use mldata::schema::HasSchema;
+//! use mldata::uci_iris;
+//!
+//! let schema = uci_iris::Data::schema();
+//! assert_eq!(schema.feature_names(), ["sepal_length", "sepal_width", "petal_length", "petal_width"]);
+//! assert_eq!(schema.target_names(), ["class"]);
+//! ```
+
+use std::fmt;
+
+/// A level of a categorical variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    /// Numeric value of the level in canonical representation
+    pub code: i32,
+
+    /// Name of the level
+    pub name: String,
+}
+
+/// The kind of values in a column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    /// Real valued quantity
+    Continuous,
+
+    /// Integer valued quantity
+    Integer,
+
+    /// Either true (1) or false (0)
+    Boolean,
+
+    /// One out of a fixed set of levels
+    Categorical(Vec<Level>),
+
+    /// Intensity of an image pixel in the range `0..=max`
+    Pixel {
+        max: u8,
+    },
+}
+
+impl Kind {
+    /// Categorical variable whose levels are coded as `0..names.len()`.
+    pub fn categorical<S: AsRef<str>>(names: &[S]) -> Kind {
+        Kind::categorical_from(0, names)
+    }
+
+    /// Categorical variable whose levels are coded as `first..first + names.len()`.
+    pub fn categorical_from<S: AsRef<str>>(first: i32, names: &[S]) -> Kind {
+        let levels = names.iter()
+            .zip(first..)
+            .map(|(name, code)| Level { code, name: name.as_ref().to_owned() })
+            .collect();
+        Kind::Categorical(levels)
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Kind::Continuous => write!(f, "continuous"),
+            Kind::Integer => write!(f, "integer"),
+            Kind::Boolean => write!(f, "boolean"),
+            Kind::Categorical(ref levels) => {
+                write!(f, "categorical (")?;
+                for (i, level) in levels.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}={}", level.code, level.name)?;
+                }
+                write!(f, ")")
+            }
+            Kind::Pixel { max } => write!(f, "pixel (0..{})", max),
+        }
+    }
+}
+
+/// Description of a single column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    /// Short identifier, suitable as a column label
+    pub name: String,
+
+    /// What the column means
+    pub description: String,
+
+    /// Unit of measurement, if any
+    pub unit: Option<String>,
+
+    /// Kind of values
+    pub kind: Kind,
+}
+
+impl Column {
+    pub fn new<N: Into<String>, D: Into<String>>(name: N, description: D, kind: Kind) -> Self {
+        Column {
+            name: name.into(),
+            description: description.into(),
+            unit: None,
+            kind,
+        }
+    }
+
+    /// Set the unit of measurement.
+    pub fn unit<U: Into<String>>(mut self, unit: U) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.description)?;
+        if let Some(ref unit) = self.unit {
+            write!(f, " [{}]", unit)?;
+        }
+        write!(f, "; {}", self.kind)
+    }
+}
+
+/// Description of all columns in canonical representation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    /// Columns of X
+    pub features: Vec<Column>,
+
+    /// Columns of Y
+    pub targets: Vec<Column>,
+}
+
+impl Schema {
+    pub fn feature_names(&self) -> Vec<&str> {
+        self.features.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    pub fn target_names(&self) -> Vec<&str> {
+        self.targets.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// Find a feature column by name and return its position in X.
+    pub fn feature(&self, name: &str) -> Option<(usize, &Column)> {
+        self.features.iter().enumerate().find(|&(_, c)| c.name == name)
+    }
+}
+
+/// Data sets that describe their canonical representation.
+pub trait HasSchema {
+    fn schema() -> Schema;
+}
+
+/// Columns of an image with `rows` x `cols` pixels, stored row by row.
+pub fn pixel_columns(rows: usize, cols: usize, max: u8) -> Vec<Column> {
+    let mut columns = Vec::with_capacity(rows * cols);
+    for r in 0..rows {
+        for c in 0..cols {
+            let name = format!("pixel_{}_{}", r, c);
+            let description = format!("intensity of the pixel in row {}, column {}", r, c);
+            columns.push(Column::new(name, description, Kind::Pixel { max }));
+        }
+    }
+    columns
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use registry;
+
+    #[test]
+    fn registered() {
+        for entry in registry::entries() {
+            let schema = entry.schema();
+            assert!(!schema.features.is_empty());
+            assert_eq!(schema.targets.len(), 1);
+
+            let mut names = schema.feature_names();
+            names.sort();
+            names.dedup();
+            assert_eq!(names.len(), schema.features.len(), "duplicate column in {}", entry.name);
+        }
+
+        let schema = registry::find("mldata/mnist-original").unwrap().schema();
+        assert_eq!(schema.features.len(), 784);
+        assert_eq!(schema.feature("pixel_1_0").map(|(i, _)| i), Some(28));
+    }
+
+    #[test]
+    fn display() {
+        let column = Column::new("origin", "country of origin", Kind::categorical_from(1, &["USA", "Europe", "Japan"]));
+        assert_eq!(column.to_string(), "origin: country of origin; categorical (1=USA, 2=Europe, 3=Japan)");
+
+        let column = Column::new("rm", "average number of rooms", Kind::Continuous).unit("rooms");
+        assert_eq!(column.to_string(), "rm: average number of rooms [rooms]; continuous");
+    }
+}
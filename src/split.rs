@@ -0,0 +1,254 @@
+//! Splitting data sets into training, testing and validation sets
+//!
+//! All functions in this module work on sample indices. Use [`select_samples`] to pick the
+//! corresponding rows from the canonical representation.
+//!
+//! Random splits are seeded, and the same seed always produces the same split, regardless of
+//! platform or machine.
+//!
+//! [`select_samples`]: fn.select_samples.html
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use mldata::canonical::CanonicalData;
+//! use mldata::split::{train_test_split, select_samples};
+//! use mldata::uci_iris::DataSet;
+//!
+//! let (x, y) = DataSet::new().create().unwrap().load_data().unwrap().into_canonical();
+//!
+//! let (train, test) = train_test_split(x.rows(), 0.2, 42);
+//! let (x_train, y_train) = select_samples(&x, &y, &train);
+//! let (x_test, y_test) = select_samples(&x, &y, &test);
+//! ```
+
+use ndarray::{Array2, ArrayView1, Axis};
+
+use utils::random::Rng;
+
+/// Indices `0..n_samples` in random order.
+pub fn shuffled_indices(n_samples: usize, seed: u64) -> Vec<usize> {
+    let mut idx: Vec<_> = (0..n_samples).collect();
+    Rng::new(seed).shuffle(&mut idx);
+    idx
+}
+
+/// Number of samples that go into a subset of the given fraction.
+fn subset_size(n_samples: usize, fraction: f64) -> usize {
+    assert!(fraction >= 0.0 && fraction <= 1.0, "fraction must be in the range [0, 1]");
+    (n_samples as f64 * fraction).round() as usize
+}
+
+/// Randomly split samples into a training and a testing set.
+///
+/// Returns the indices of the training and testing samples.
+pub fn train_test_split(n_samples: usize, test_fraction: f64, seed: u64) -> (Vec<usize>, Vec<usize>) {
+    let mut train = shuffled_indices(n_samples, seed);
+    let test = train.split_off(n_samples - subset_size(n_samples, test_fraction));
+    (train, test)
+}
+
+/// Randomly split samples into a training, a testing and a validation set.
+///
+/// Returns the indices of the training, testing and validation samples.
+pub fn train_test_validation_split(n_samples: usize, test_fraction: f64, validation_fraction: f64, seed: u64) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+    let n_test = subset_size(n_samples, test_fraction);
+    let n_validation = subset_size(n_samples, validation_fraction);
+    assert!(n_test + n_validation <= n_samples, "test and validation fractions exceed the data set");
+
+    let mut train = shuffled_indices(n_samples, seed);
+    let mut test = train.split_off(n_samples - n_test - n_validation);
+    let validation = test.split_off(n_test);
+    (train, test, validation)
+}
+
+/// Randomly split samples into a training and a testing set, preserving class proportions.
+///
+/// `labels` contains the class of each sample, e.g. the first column of a classification target
+/// in canonical representation. Each class is split separately, so that it is represented in the
+/// training and testing set with the same proportion as in the whole data set.
+///
+/// Returns the indices of the training and testing samples.
+pub fn stratified_train_test_split<T: Copy + PartialEq>(labels: ArrayView1<T>, test_fraction: f64, seed: u64) -> (Vec<usize>, Vec<usize>) {
+    let mut classes: Vec<(T, Vec<usize>)> = Vec::new();
+    for (i, &label) in labels.iter().enumerate() {
+        match classes.iter().position(|&(c, _)| c == label) {
+            Some(k) => classes[k].1.push(i),
+            None => classes.push((label, vec![i])),
+        }
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut train = Vec::new();
+    let mut test = Vec::new();
+
+    for (_, mut idx) in classes {
+        rng.shuffle(&mut idx);
+        let n_train = idx.len() - subset_size(idx.len(), test_fraction);
+        test.extend_from_slice(&idx[n_train..]);
+        idx.truncate(n_train);
+        train.extend(idx);
+    }
+
+    // don't leave the samples ordered by class
+    rng.shuffle(&mut train);
+    rng.shuffle(&mut test);
+
+    (train, test)
+}
+
+/// Pick the samples at the given indices from the canonical representation.
+pub fn select_samples<T: Copy>(x: &Array2<T>, y: &Array2<T>, idx: &[usize]) -> (Array2<T>, Array2<T>) {
+    (x.select(Axis(0), idx), y.select(Axis(0), idx))
+}
+
+/// K-fold cross-validation iterator.
+///
+/// Partitions the samples into `n_folds` folds of (almost) equal size. Each iteration yields the
+/// indices of the training and testing samples, where each fold is used exactly once for testing.
+pub struct KFold {
+    idx: Vec<usize>,
+    n_folds: usize,
+    fold: usize,
+}
+
+impl KFold {
+    /// Folds of consecutive samples.
+    pub fn new(n_samples: usize, n_folds: usize) -> Self {
+        KFold::from_indices((0..n_samples).collect(), n_folds)
+    }
+
+    /// Folds of randomly chosen samples.
+    pub fn shuffled(n_samples: usize, n_folds: usize, seed: u64) -> Self {
+        KFold::from_indices(shuffled_indices(n_samples, seed), n_folds)
+    }
+
+    fn from_indices(idx: Vec<usize>, n_folds: usize) -> Self {
+        assert!(n_folds >= 2, "need at least two folds");
+        assert!(n_folds <= idx.len(), "more folds than samples");
+        KFold {
+            idx,
+            n_folds,
+            fold: 0,
+        }
+    }
+
+    fn fold_start(&self, fold: usize) -> usize {
+        fold * self.idx.len() / self.n_folds
+    }
+}
+
+impl Iterator for KFold {
+    type Item = (Vec<usize>, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.fold >= self.n_folds {
+            return None
+        }
+
+        let start = self.fold_start(self.fold);
+        let end = self.fold_start(self.fold + 1);
+        self.fold += 1;
+
+        let test = self.idx[start..end].to_vec();
+        let train = self.idx[..start].iter().chain(&self.idx[end..]).cloned().collect();
+        Some((train, test))
+    }
+}
+
+/// Leave-one-out cross-validation iterator.
+///
+/// Each iteration yields the indices of the training samples and the index of the single
+/// testing sample.
+pub struct LeaveOneOut {
+    n_samples: usize,
+    current: usize,
+}
+
+impl LeaveOneOut {
+    pub fn new(n_samples: usize) -> Self {
+        LeaveOneOut {
+            n_samples,
+            current: 0,
+        }
+    }
+}
+
+impl Iterator for LeaveOneOut {
+    type Item = (Vec<usize>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current >= self.n_samples {
+            return None
+        }
+
+        let test = self.current;
+        self.current += 1;
+
+        let train = (0..self.n_samples).filter(|&i| i != test).collect();
+        Some((train, test))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array1;
+    use super::*;
+
+    fn sorted(mut v: Vec<usize>) -> Vec<usize> {
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn random_split() {
+        let (train, test) = train_test_split(150, 0.2, 42);
+        assert_eq!(train.len(), 120);
+        assert_eq!(test.len(), 30);
+        assert_eq!(sorted(train.iter().chain(&test).cloned().collect()), (0..150).collect::<Vec<_>>());
+
+        assert_eq!(train_test_split(150, 0.2, 42), (train, test));
+        assert_ne!(train_test_split(150, 0.2, 42), train_test_split(150, 0.2, 43));
+
+        let (train, test, validation) = train_test_validation_split(100, 0.2, 0.1, 7);
+        assert_eq!((train.len(), test.len(), validation.len()), (70, 20, 10));
+        let all = train.into_iter().chain(test).chain(validation).collect();
+        assert_eq!(sorted(all), (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stratified_split() {
+        let labels: Array1<f64> = (0..150).map(|i| (i / 50) as f64).collect();
+        let (train, test) = stratified_train_test_split(labels.view(), 0.2, 42);
+        assert_eq!(train.len(), 120);
+        assert_eq!(test.len(), 30);
+
+        for class in 0..3 {
+            let n = test.iter().filter(|&&i| labels[i] == class as f64).count();
+            assert_eq!(n, 10);
+        }
+    }
+
+    #[test]
+    fn k_fold() {
+        let folds: Vec<_> = KFold::shuffled(10, 3, 42).collect();
+        assert_eq!(folds.len(), 3);
+        assert_eq!(folds.iter().map(|f| f.1.len()).collect::<Vec<_>>(), [3, 3, 4]);
+
+        let all_test = folds.iter().flat_map(|f| f.1.iter().cloned()).collect();
+        assert_eq!(sorted(all_test), (0..10).collect::<Vec<_>>());
+
+        for (train, test) in folds {
+            assert_eq!(sorted(train.into_iter().chain(test).collect()), (0..10).collect::<Vec<_>>());
+        }
+
+        assert_eq!(KFold::new(4, 2).next(), Some((vec![2, 3], vec![0, 1])));
+    }
+
+    #[test]
+    fn leave_one_out() {
+        let folds: Vec<_> = LeaveOneOut::new(3).collect();
+        assert_eq!(folds, [(vec![1, 2], 0), (vec![0, 2], 1), (vec![0, 1], 2)]);
+    }
+}
@@ -4,37 +4,77 @@ use std::fs;
 use std::io::{BufRead, BufReader, Read};
 use std::path;
 
-use app_dirs::*;
-use ndarray::Array2;
-
-use utils::downloader::assure_file;
-use utils::error::Error;
-
-use canonical::CanonicalData;
-use common::APP_INFO;
+use ndarray::{Array2, ArrayViewMut1};
+
+use utils::downloader::{Downloader, RemoteFile};
+use utils::error::{Context, Error};
+use utils::parse::Fields;
+
+use canonical::{CanonicalDataAs, CanonicalSamples, Real};
+use common::data_dir;
+use dataset::{Dataset, Loader};
+use schema::{Column, HasSchema, Kind, Schema};
+
+const DATA_FILE: RemoteFile = RemoteFile {
+    name: "auto_mpg.data",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/auto-mpg/auto-mpg.data",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/auto-mpg/auto-mpg.data",
+    ],
+    sha256: None,
+    size: None,
+};
+
+const INFO_FILE: RemoteFile = RemoteFile {
+    name: "auto_mpg.names",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/auto-mpg/auto-mpg.names",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/auto-mpg/auto-mpg.names",
+    ],
+    sha256: None,
+    size: None,
+};
+
+/// All files of the data set
+pub const FILES: &[RemoteFile] = &[DATA_FILE, INFO_FILE];
+
+/// Directory of the data set, relative to the data home
+pub const SUBDIR: &str = "UCI/auto_mpg";
 
 /// Configure the loader for the data set.
 ///
 /// This structure implements the builder pattern to configure the [`DataSetLoader`].
 pub struct DataSet {
-    data_root: path::PathBuf,
+    data_root: Option<path::PathBuf>,
     download: bool,
+    downloader: Downloader,
+}
+
+impl Default for DataSet {
+    fn default() -> Self {
+        DataSet::new()
+    }
 }
 
 impl DataSet {
     pub fn new() -> Self {
         DataSet {
-            data_root: get_app_dir(AppDataType::UserData, &APP_INFO, "UCI/auto_mpg").unwrap(),
+            data_root: None,
             download: true,
+            downloader: Downloader::new(),
         }
     }
 
     pub fn create(&self) -> Result<DataSetLoader, Error> {
-        DataSetLoader::new(&self.data_root, self.download)
+        let data_root = match self.data_root {
+            Some(ref path) => path.clone(),
+            None => data_dir(SUBDIR)?,
+        };
+        DataSetLoader::with_downloader(&data_root, self.download, &self.downloader)
     }
 
     pub fn data_root<P: AsRef<path::Path>>(&mut self, p: P) -> &mut Self {
-        self.data_root = p.as_ref().into();
+        self.data_root = Some(p.as_ref().into());
         self
     }
 
@@ -42,6 +82,8 @@ impl DataSet {
         self.download = b;
         self
     }
+
+    downloader_options!();
 }
 
 /// Load the data set.
@@ -57,15 +99,22 @@ pub struct DataSetLoader {
 impl DataSetLoader {
     /// new
     pub fn new<P: AsRef<path::Path>>(data_path: P, download: bool) -> Result<DataSetLoader, Error> {
+        DataSetLoader::with_downloader(data_path, download, &Downloader::new())
+    }
+
+    /// Like `new`, but download missing files with the given `downloader`.
+    pub fn with_downloader<P: AsRef<path::Path>>(data_path: P, download: bool, downloader: &Downloader) -> Result<DataSetLoader, Error> {
         let data_path = data_path.as_ref();
         fs::create_dir_all(data_path)?;
 
-        let data_file = data_path.join("auto_mpg.data");
-        let info_file = data_path.join("auto_mpg.names");
+        let data_file = data_path.join(DATA_FILE.name);
+        let info_file = data_path.join(INFO_FILE.name);
 
         if download {
-            assure_file(&data_file, "http://archive.ics.uci.edu/ml/machine-learning-databases/auto-mpg/auto-mpg.data")?;
-            assure_file(&info_file, "http://archive.ics.uci.edu/ml/machine-learning-databases/auto-mpg/auto-mpg.names")?;
+            downloader.assure_files(&[
+                (&data_file, &DATA_FILE),
+                (&info_file, &INFO_FILE),
+            ])?;
         }
 
         Ok(DataSetLoader{
@@ -74,55 +123,84 @@ impl DataSetLoader {
         })
     }
 
+    /// Check that all files of the data set are complete and unmodified.
+    pub fn verify(&self) -> Result<(), Error> {
+        DATA_FILE.verify(&self.data_file)?;
+        INFO_FILE.verify(&self.info_file)?;
+        Ok(())
+    }
+
     pub fn load_info(&self) -> Result<String, Error> {
-        let mut file = fs::File::open(&self.info_file)?;
+        let mut file = fs::File::open(&self.info_file).in_file(&self.info_file)?;
 
         let mut info = String::new();
-        file.read_to_string(&mut info)?;
+        file.read_to_string(&mut info).in_file(&self.info_file)?;
 
         Ok(info)
     }
 
     pub fn load_data(&self) -> Result<Data, Error> {
-        let input = BufReader::new(fs::File::open(&self.data_file)?);
+        let input = BufReader::new(fs::File::open(&self.data_file).in_file(&self.data_file)?);
+        parse(input, &self.data_file)
+    }
+}
 
-        let mut x = Vec::new();
-        let mut y = Vec::new();
+fn parse<R: BufRead>(input: R, file: &path::Path) -> Result<Data, Error> {
+    let mut x = Vec::new();
+    let mut y = Vec::new();
 
-        for line in input.lines() {
-            let line = line?;
-            if line.is_empty() {
-                continue
-            }
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue
+        }
 
-            let mut cols = line.split_whitespace();
-
-            let yi = TargetVar {
-                mpg: cols.next().unwrap().parse().unwrap(),
-            };
-
-            let xi = FeatureRow {
-                cylinders: cols.next().unwrap().parse().unwrap(),
-                displacement: cols.next().unwrap().parse().unwrap(),
-                horsepower: match cols.next().unwrap() {
-                    "?" => ::std::f32::NAN,
-                    nr => nr.parse().unwrap(),
-                },
-                weight: cols.next().unwrap().parse().unwrap(),
-                acceleration: cols.next().unwrap().parse().unwrap(),
-                model_year: cols.next().unwrap().parse().unwrap(),
-                origin: cols.next().unwrap().parse().unwrap(),
-                car_name: {
-                    let name = cols.collect::<Vec<_>>().join(" ");
-                    name[1..name.len()-1].to_owned()  // remove enclosing "s
+        let mut cols = Fields::new(file, i + 1, line.split_whitespace());
+
+        let yi = TargetVar {
+            mpg: cols.next("number")?,
+        };
+
+        let xi = FeatureRow {
+            cylinders: cols.next("integer")?,
+            displacement: cols.next("number")?,
+            horsepower: match cols.next_str("number or '?'")? {
+                "?" => f32::NAN,
+                nr => nr.parse().map_err(|_| cols.error(nr, "number or '?'"))?,
+            },
+            weight: cols.next("number")?,
+            acceleration: cols.next("number")?,
+            model_year: cols.next("integer")?,
+            origin: cols.next("integer")?,
+            car_name: {
+                let name = cols.rest().join(" ");
+                if name.len() < 2 || !name.starts_with('"') || !name.ends_with('"') {
+                    return Err(cols.error(&name, "quoted car name"))
                 }
-            };
+                name[1..name.len()-1].to_owned()  // remove enclosing "s
+            }
+        };
 
-            x.push(xi);
-            y.push(yi);
-        }
+        x.push(xi);
+        y.push(yi);
+    }
+
+    Ok(Data::from(x, y))
+}
+
+impl Loader for DataSetLoader {
+    type Data = Data;
+
+    fn load_info(&self) -> Result<String, Error> {
+        DataSetLoader::load_info(self)
+    }
+
+    fn load_data(&self) -> Result<Data, Error> {
+        DataSetLoader::load_data(self)
+    }
 
-        Ok(Data::from(x, y))
+    fn verify(&self) -> Result<(), Error> {
+        DataSetLoader::verify(self)
     }
 }
 
@@ -169,28 +247,74 @@ impl Data {
     }
 }
 
-impl CanonicalData for Data {
-    fn to_canonical(&self) -> (Array2<f64>, Array2<f64>) {
-        let mut x_tmp = Vec::new();
-        for xi in self.x.iter() {
-            x_tmp.push(xi.cylinders as f64);
-            x_tmp.push(xi.displacement as f64);
-            x_tmp.push(xi.horsepower as f64);
-            x_tmp.push(xi.weight as f64);
-            x_tmp.push(xi.acceleration as f64);
-            x_tmp.push(xi.model_year as f64);
-            x_tmp.push(xi.origin as f64);
+impl Dataset for Data {
+    const NAME: &'static str = "uci/auto-mpg";
+
+    type Features<'a> = &'a FeatureRow;
+    type Target = TargetVar;
+    type Loader = DataSetLoader;
+
+    fn loader() -> Result<DataSetLoader, Error> {
+        DataSet::new().create()
+    }
+
+    fn n_samples(&self) -> usize {
+        Data::n_samples(self)
+    }
+
+    fn get_sample(&self, idx: usize) -> (&FeatureRow, TargetVar) {
+        Data::get_sample(self, idx)
+    }
+}
+
+impl HasSchema for Data {
+    fn schema() -> Schema {
+        Schema {
+            features: vec![
+                Column::new("cylinders", "number of cylinders", Kind::Integer),
+                Column::new("displacement", "engine displacement", Kind::Continuous).unit("cu. in."),
+                Column::new("horsepower", "engine power", Kind::Continuous).unit("hp"),
+                Column::new("weight", "vehicle weight", Kind::Continuous).unit("lbs"),
+                Column::new("acceleration", "time to accelerate from 0 to 60 mph", Kind::Continuous).unit("s"),
+                Column::new("model_year", "model year (last two digits)", Kind::Integer),
+                Column::new("origin", "region of origin", Kind::categorical_from(1, &["USA", "Europe", "Japan"])),
+            ],
+            targets: vec![
+                Column::new("mpg", "fuel consumption", Kind::Continuous).unit("miles per gallon"),
+            ],
         }
-        let y_tmp = self.y.iter().map(|yi| yi.mpg as f64).collect();
-        let x = Array2::from_shape_vec((self.n_samples, 7), x_tmp).unwrap();
-        let y = Array2::from_shape_vec((self.n_samples, 1), y_tmp).unwrap();
-        (x, y)
+    }
+}
+
+impl<T: Real> CanonicalDataAs<T> for Data {
+    fn to_canonical_as(&self) -> (Array2<T>, Array2<T>) {
+        let idx: Vec<_> = (0..self.n_samples).collect();
+        self.select_canonical(&idx)
+    }
+}
+
+impl<T: Real> CanonicalSamples<T> for Data {
+    fn canonical_shape(&self) -> (usize, usize, usize) {
+        (self.n_samples, 7, 1)
+    }
+
+    fn write_canonical(&self, idx: usize, mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>) {
+        let (xi, yi) = self.get_sample(idx);
+        x[0] = T::from(xi.cylinders);
+        x[1] = T::from(xi.displacement);
+        x[2] = T::from(xi.horsepower);
+        x[3] = T::from(xi.weight);
+        x[4] = T::from(xi.acceleration);
+        x[5] = T::from(xi.model_year);
+        x[6] = T::from(xi.origin);
+        y[0] = T::from(yi.mpg);
     }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use canonical::CanonicalData;
     use super::*;
 
     #[test]
@@ -232,4 +356,29 @@ mod tests {
         assert_eq!(x[[41, 5]], 71.0);
         assert_eq!(x[[41, 6]], 1.0);
     }
+
+    #[test]
+    fn parse_errors() {
+        let file = path::Path::new("auto-mpg.data");
+        let line = "15.0   8   350.0      ?      3693.      11.5   70  1\t\"buick skylark 320\"\n";
+
+        let data = parse(line.as_bytes(), file).unwrap();
+        assert!(data.get_sample(0).0.horsepower.is_nan());
+        assert_eq!(data.get_sample(0).0.car_name, "buick skylark 320");
+
+        match parse(line.replace("3693.", "36x3.").as_bytes(), file) {
+            Err(Error::Parse(e)) => assert_eq!((e.line, e.column, e.token.as_str()), (1, 5, "36x3.")),
+            _ => panic!("expected parse error"),
+        }
+
+        match parse(&line.as_bytes()[..40], file) {
+            Err(Error::Parse(e)) => assert_eq!((e.column, e.token.as_str()), (6, "")),
+            _ => panic!("expected parse error"),
+        }
+
+        match parse(line.replace("\"buick", "buick").as_bytes(), file) {
+            Err(Error::Parse(e)) => assert_eq!((e.column, e.expected), (9, "quoted car name")),
+            _ => panic!("expected parse error"),
+        }
+    }
 }
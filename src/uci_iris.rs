@@ -3,38 +3,79 @@
 use std::fs;
 use std::io::{BufRead, BufReader, Read};
 use std::path;
-
-use app_dirs::*;
-use ndarray::Array2;
-
-use utils::downloader::assure_file;
-use utils::error::Error;
-
-use canonical::CanonicalData;
-use common::APP_INFO;
+use std::str::FromStr;
+
+use ndarray::{Array2, ArrayViewMut1};
+
+use utils::downloader::{Downloader, RemoteFile};
+use utils::error::{Context, Error};
+use utils::parse::Fields;
+
+use canonical::{CanonicalDataAs, CanonicalSamples, Real};
+use common::data_dir;
+use dataset::{Dataset, Loader};
+use schema::{Column, HasSchema, Kind, Schema};
+
+const DATA_FILE: RemoteFile = RemoteFile {
+    name: "iris.data",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/iris/iris.data",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/iris/iris.data",
+    ],
+    sha256: None,
+    size: None,
+};
+
+const INFO_FILE: RemoteFile = RemoteFile {
+    name: "iris.names",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/iris/iris.names",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/iris/iris.names",
+    ],
+    sha256: None,
+    size: None,
+};
+
+/// All files of the data set
+pub const FILES: &[RemoteFile] = &[DATA_FILE, INFO_FILE];
+
+/// Directory of the data set, relative to the data home
+pub const SUBDIR: &str = "UCI/iris";
 
 /// Configure the loader for the data set.
 ///
 /// This structure implements the builder pattern to configure the [`DataSetLoader`].
 pub struct DataSet {
-    data_root: path::PathBuf,
+    data_root: Option<path::PathBuf>,
     download: bool,
+    downloader: Downloader,
+}
+
+impl Default for DataSet {
+    fn default() -> Self {
+        DataSet::new()
+    }
 }
 
 impl DataSet {
     pub fn new() -> Self {
         DataSet {
-            data_root: get_app_dir(AppDataType::UserData, &APP_INFO, "UCI/iris").unwrap(),
+            data_root: None,
             download: true,
+            downloader: Downloader::new(),
         }
     }
 
     pub fn create(&self) -> Result<DataSetLoader, Error> {
-        DataSetLoader::new(&self.data_root, self.download)
+        let data_root = match self.data_root {
+            Some(ref path) => path.clone(),
+            None => data_dir(SUBDIR)?,
+        };
+        DataSetLoader::with_downloader(&data_root, self.download, &self.downloader)
     }
 
     pub fn data_root<P: AsRef<path::Path>>(&mut self, p: P) -> &mut Self {
-        self.data_root = p.as_ref().into();
+        self.data_root = Some(p.as_ref().into());
         self
     }
 
@@ -42,6 +83,8 @@ impl DataSet {
         self.download = b;
         self
     }
+
+    downloader_options!();
 }
 
 /// Load the data set.
@@ -57,15 +100,22 @@ pub struct DataSetLoader {
 impl DataSetLoader {
     /// new
     pub fn new<P: AsRef<path::Path>>(data_path: P, download: bool) -> Result<DataSetLoader, Error> {
+        DataSetLoader::with_downloader(data_path, download, &Downloader::new())
+    }
+
+    /// Like `new`, but download missing files with the given `downloader`.
+    pub fn with_downloader<P: AsRef<path::Path>>(data_path: P, download: bool, downloader: &Downloader) -> Result<DataSetLoader, Error> {
         let data_path = data_path.as_ref();
         fs::create_dir_all(data_path)?;
 
-        let data_file = data_path.join("iris.data");
-        let info_file = data_path.join("iris.names");
+        let data_file = data_path.join(DATA_FILE.name);
+        let info_file = data_path.join(INFO_FILE.name);
 
         if download {
-            assure_file(&data_file, "http://archive.ics.uci.edu/ml/machine-learning-databases/iris/iris.data")?;
-            assure_file(&info_file, "http://archive.ics.uci.edu/ml/machine-learning-databases/iris/iris.names")?;
+            downloader.assure_files(&[
+                (&data_file, &DATA_FILE),
+                (&info_file, &INFO_FILE),
+            ])?;
         }
 
         Ok(DataSetLoader{
@@ -74,34 +124,60 @@ impl DataSetLoader {
         })
     }
 
+    /// Check that all files of the data set are complete and unmodified.
+    pub fn verify(&self) -> Result<(), Error> {
+        DATA_FILE.verify(&self.data_file)?;
+        INFO_FILE.verify(&self.info_file)?;
+        Ok(())
+    }
+
     pub fn load_info(&self) -> Result<String, Error> {
-        let mut file = fs::File::open(&self.info_file)?;
+        let mut file = fs::File::open(&self.info_file).in_file(&self.info_file)?;
 
         let mut info = String::new();
-        file.read_to_string(&mut info)?;
+        file.read_to_string(&mut info).in_file(&self.info_file)?;
 
         Ok(info)
     }
 
     pub fn load_data(&self) -> Result<Data, Error> {
-        let input = BufReader::new(fs::File::open(&self.data_file)?);
-
-        let mut x = Vec::new();
-        let mut y = Vec::new();
-
-        for line in input.lines() {
-            let line = line?;
-            if line.is_empty() {
-                continue
-            }
-            let elements: Vec<_> = line.split(",").collect();
-            for e in &elements[0..4] {
-                x.push(e.parse().unwrap());
-            }
-            y.push(elements[4].into());
+        let input = BufReader::new(fs::File::open(&self.data_file).in_file(&self.data_file)?);
+        parse(input, &self.data_file)
+    }
+}
+
+fn parse<R: BufRead>(input: R, file: &path::Path) -> Result<Data, Error> {
+    let mut x = Vec::new();
+    let mut y = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue
+        }
+        let mut fields = Fields::new(file, i + 1, line.split(","));
+        for _ in 0..4 {
+            x.push(fields.next("number")?);
         }
+        y.push(fields.next("iris class")?);
+    }
 
-        Ok(Data::from(x, y))
+    Ok(Data::from(x, y))
+}
+
+impl Loader for DataSetLoader {
+    type Data = Data;
+
+    fn load_info(&self) -> Result<String, Error> {
+        DataSetLoader::load_info(self)
+    }
+
+    fn load_data(&self) -> Result<Data, Error> {
+        DataSetLoader::load_data(self)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        DataSetLoader::verify(self)
     }
 }
 
@@ -112,17 +188,34 @@ pub enum Iris {
     Virginica,
 }
 
-impl<'a> From<&'a str> for Iris {
-    fn from(s: &str) -> Iris {
+/// Error returned when parsing an unknown class label.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct UnknownClass;
+
+impl FromStr for Iris {
+    type Err = UnknownClass;
+
+    fn from_str(s: &str) -> Result<Iris, UnknownClass> {
         match s {
-            "Iris-setosa" => Iris::Setosa,
-            "Iris-versicolor" => Iris::Versicolor,
-            "Iris-virginica" => Iris::Virginica,
-            _ => panic!("Cannot convert string to Iris")
+            "Iris-setosa" => Ok(Iris::Setosa),
+            "Iris-versicolor" => Ok(Iris::Versicolor),
+            "Iris-virginica" => Ok(Iris::Virginica),
+            _ => Err(UnknownClass),
         }
     }
 }
 
+/// Deprecated: use `str::parse` instead, which does not panic on unknown class labels.
+///
+/// # Panics
+///
+/// If `s` is not a class label of the Iris data set.
+impl<'a> From<&'a str> for Iris {
+    fn from(s: &str) -> Iris {
+        s.parse().expect("Cannot convert string to Iris")
+    }
+}
+
 /// In-memory representation of the data
 pub struct Data {
     x: Vec<f32>,
@@ -151,19 +244,71 @@ impl Data {
     }
 }
 
-impl CanonicalData for Data {
-    fn to_canonical(&self) -> (Array2<f64>, Array2<f64>) {
-        let x_tmp = self.x.iter().map(|f| *f as f64).collect();
-        let y_tmp = self.y.iter().map(|f| *f as usize as f64).collect();
+impl Dataset for Data {
+    const NAME: &'static str = "uci/iris";
+
+    type Features<'a> = &'a [f32];
+    type Target = Iris;
+    type Loader = DataSetLoader;
+
+    fn loader() -> Result<DataSetLoader, Error> {
+        DataSet::new().create()
+    }
+
+    fn n_samples(&self) -> usize {
+        Data::n_samples(self)
+    }
+
+    fn get_sample(&self, idx: usize) -> (&[f32], Iris) {
+        Data::get_sample(self, idx)
+    }
+}
+
+impl HasSchema for Data {
+    fn schema() -> Schema {
+        Schema {
+            features: vec![
+                Column::new("sepal_length", "sepal length", Kind::Continuous).unit("cm"),
+                Column::new("sepal_width", "sepal width", Kind::Continuous).unit("cm"),
+                Column::new("petal_length", "petal length", Kind::Continuous).unit("cm"),
+                Column::new("petal_width", "petal width", Kind::Continuous).unit("cm"),
+            ],
+            targets: vec![
+                Column::new("class", "species of iris",
+                            Kind::categorical(&["Iris-setosa", "Iris-versicolor", "Iris-virginica"])),
+            ],
+        }
+    }
+}
+
+impl<T: Real> CanonicalDataAs<T> for Data {
+    fn to_canonical_as(&self) -> (Array2<T>, Array2<T>) {
+        let x_tmp = self.x.iter().map(|&f| T::from(f)).collect();
+        let y_tmp = self.y.iter().map(|&f| T::from(f as u8)).collect();
         let x = Array2::from_shape_vec((self.n_samples, 4), x_tmp).unwrap();
         let y = Array2::from_shape_vec((self.n_samples, 1), y_tmp).unwrap();
         (x, y)
     }
 }
 
+impl<T: Real> CanonicalSamples<T> for Data {
+    fn canonical_shape(&self) -> (usize, usize, usize) {
+        (self.n_samples, 4, 1)
+    }
+
+    fn write_canonical(&self, idx: usize, mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>) {
+        let (xi, yi) = self.get_sample(idx);
+        for (out, &f) in x.iter_mut().zip(xi) {
+            *out = T::from(f);
+        }
+        y[0] = T::from(yi as u8);
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
+    use canonical::CanonicalData;
     use super::*;
 
     #[test]
@@ -189,5 +334,34 @@ mod tests {
         assert_eq!(y[[25, 0]], 0.0);
         assert_eq!(y[[75, 0]], 1.0);
         assert_eq!(y[[125, 0]], 2.0);
+
+        let (x, y): (Array2<f32>, Array2<f32>) = data.load_data().unwrap().into_canonical_as();
+        assert_eq!(x.shape(), [150, 4]);
+        assert_eq!(y[[125, 0]], 2.0);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let file = path::Path::new("iris.data");
+
+        let data = parse("5.1,3.5,1.4,0.2,Iris-setosa\n\n".as_bytes(), file).unwrap();
+        assert_eq!(data.n_samples(), 1);
+
+        match parse("5.1,3.5,1.4,0.2,Iris-setosa\n5.1,3.5,1.4,0.2,Iris-rosea\n".as_bytes(), file) {
+            Err(Error::Parse(e)) => assert_eq!((e.line, e.column, e.token.as_str()), (2, 5, "Iris-rosea")),
+            _ => panic!("expected parse error"),
+        }
+
+        match parse("5.1,3.5,1.4".as_bytes(), file) {
+            Err(Error::Parse(e)) => assert_eq!((e.line, e.column, e.token.as_str()), (1, 4, "")),
+            _ => panic!("expected parse error"),
+        }
+    }
+
+    #[test]
+    fn class_labels() {
+        assert_eq!("Iris-virginica".parse(), Ok(Iris::Virginica));
+        assert_eq!("Iris-rosea".parse::<Iris>(), Err(UnknownClass));
+        assert_eq!(Iris::from("Iris-setosa"), Iris::Setosa);
     }
 }
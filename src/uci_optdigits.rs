@@ -4,38 +4,98 @@ use std::fs;
 use std::io::Read;
 use std::path;
 
-use app_dirs::*;
-use ndarray::{Array2, ArrayView2, ShapeBuilder, Zip};
+use ndarray::{Array2, ArrayView2, ArrayViewMut1, ShapeBuilder, Zip};
 
-use utils::downloader::assure_file;
-use utils::error::Error;
+use utils::downloader::{Downloader, RemoteFile};
+use utils::error::{Context, Error, ParseError};
 use utils::lzw;
 
-use canonical::CanonicalData;
-use common::APP_INFO;
+use canonical::{CanonicalDataAs, CanonicalSamples, CanonicalView};
+use common::data_dir;
+use dataset::{Dataset, Loader, Split};
+use schema::{pixel_columns, Column, HasSchema, Kind, Schema};
+
+const TRAINING_FILE: RemoteFile = RemoteFile {
+    name: "optdigits-orig.tra.Z",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.tra.Z",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.tra.Z",
+    ],
+    sha256: None,
+    size: None,
+};
+
+const VALIDATION_FILE: RemoteFile = RemoteFile {
+    name: "optdigits-orig.cv.Z",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.cv.Z",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.cv.Z",
+    ],
+    sha256: None,
+    size: None,
+};
+
+/// The writer-independent testing set, which is the one published results refer to
+const TESTING_FILE: RemoteFile = RemoteFile {
+    name: "optdigits-orig.windep.Z",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.windep.Z",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.windep.Z",
+    ],
+    sha256: None,
+    size: None,
+};
+
+const INFO_FILE: RemoteFile = RemoteFile {
+    name: "optdigits-orig.names",
+    urls: &[
+        "https://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.names",
+        "http://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.names",
+    ],
+    sha256: None,
+    size: None,
+};
+
+/// All files of the data set
+pub const FILES: &[RemoteFile] = &[TRAINING_FILE, VALIDATION_FILE, TESTING_FILE, INFO_FILE];
+
+/// Directory of the data set, relative to the data home
+pub const SUBDIR: &str = "UCI/optdigits";
 
 /// Configure the loader for the data set.
 ///
 /// This structure implements the builder pattern to configure the [`DataSetLoader`].
 pub struct DataSet {
-    data_root: path::PathBuf,
+    data_root: Option<path::PathBuf>,
     download: bool,
+    downloader: Downloader,
+}
+
+impl Default for DataSet {
+    fn default() -> Self {
+        DataSet::new()
+    }
 }
 
 impl DataSet {
     pub fn new() -> Self {
         DataSet {
-            data_root: get_app_dir(AppDataType::UserData, &APP_INFO, "UCI/optdigits").unwrap(),
+            data_root: None,
             download: true,
+            downloader: Downloader::new(),
         }
     }
 
     pub fn create(&self) -> Result<DataSetLoader, Error> {
-        DataSetLoader::new(&self.data_root, self.download)
+        let data_root = match self.data_root {
+            Some(ref path) => path.clone(),
+            None => data_dir(SUBDIR)?,
+        };
+        DataSetLoader::with_downloader(&data_root, self.download, &self.downloader)
     }
 
     pub fn data_root<P: AsRef<path::Path>>(&mut self, p: P) -> &mut Self {
-        self.data_root = p.as_ref().into();
+        self.data_root = Some(p.as_ref().into());
         self
     }
 
@@ -43,6 +103,8 @@ impl DataSet {
         self.download = b;
         self
     }
+
+    downloader_options!();
 }
 
 /// Load the data set.
@@ -52,6 +114,7 @@ impl DataSet {
 /// set all options in the arguments.
 pub struct DataSetLoader {
     training_file: path::PathBuf,
+    validation_file: path::PathBuf,
     testing_file: path::PathBuf,
     info_file: path::PathBuf,
 }
@@ -59,73 +122,151 @@ pub struct DataSetLoader {
 impl DataSetLoader {
     /// new
     pub fn new<P: AsRef<path::Path>>(data_path: P, download: bool) -> Result<DataSetLoader, Error> {
+        DataSetLoader::with_downloader(data_path, download, &Downloader::new())
+    }
+
+    /// Like `new`, but download missing files with the given `downloader`.
+    pub fn with_downloader<P: AsRef<path::Path>>(data_path: P, download: bool, downloader: &Downloader) -> Result<DataSetLoader, Error> {
         let data_path = data_path.as_ref();
         fs::create_dir_all(data_path)?;
 
-        let training_file = data_path.join("optdigits-orig.tra.Z");
-        let testing_file = data_path.join("optdigits-orig.cv.Z");
-        let info_file = data_path.join("optdigits-orig.names");
+        let training_file = data_path.join(TRAINING_FILE.name);
+        let validation_file = data_path.join(VALIDATION_FILE.name);
+        let testing_file = data_path.join(TESTING_FILE.name);
+        let info_file = data_path.join(INFO_FILE.name);
 
         if download {
-            assure_file(&training_file, "http://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.tra.Z")?;
-            assure_file(&testing_file, "http://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.cv.Z")?;
-            assure_file(&info_file, "http://archive.ics.uci.edu/ml/machine-learning-databases/optdigits/optdigits-orig.names")?;
+            downloader.assure_files(&[
+                (&training_file, &TRAINING_FILE),
+                (&validation_file, &VALIDATION_FILE),
+                (&testing_file, &TESTING_FILE),
+                (&info_file, &INFO_FILE),
+            ])?;
         }
 
         Ok(DataSetLoader{
             training_file,
+            validation_file,
             testing_file,
             info_file,
         })
     }
 
+    /// Load training, validation and testing data combined into one data set.
+    pub fn load_data(&self) -> Result<Data, Error> {
+        let mut data = self.load_training_data()?;
+        data.append(self.load_validation_data()?);
+        data.append(self.load_testing_data()?);
+        Ok(data)
+    }
+
+    /// Load the official training, validation or testing set, or all of them combined.
+    pub fn load_split(&self, split: Split) -> Result<Data, Error> {
+        match split {
+            Split::All => self.load_data(),
+            Split::Train => self.load_training_data(),
+            Split::Test => self.load_testing_data(),
+            Split::Validation => self.load_validation_data(),
+        }
+    }
+
     pub fn load_training_data(&self) -> Result<Data, Error> {
-        self.load_data(&self.training_file)
+        self.load_file(&self.training_file)
+    }
+
+    /// Load the validation set, which is written by the same people as the training set.
+    pub fn load_validation_data(&self) -> Result<Data, Error> {
+        self.load_file(&self.validation_file)
     }
 
+    /// Load the writer-independent testing set, which is written by other people than the
+    /// training and validation sets.
     pub fn load_testing_data(&self) -> Result<Data, Error> {
-        self.load_data(&self.testing_file)
+        self.load_file(&self.testing_file)
+    }
+
+    /// Check that all files of the data set are complete and unmodified.
+    pub fn verify(&self) -> Result<(), Error> {
+        TRAINING_FILE.verify(&self.training_file)?;
+        VALIDATION_FILE.verify(&self.validation_file)?;
+        TESTING_FILE.verify(&self.testing_file)?;
+        INFO_FILE.verify(&self.info_file)?;
+        Ok(())
     }
 
     pub fn load_info(&self) -> Result<String, Error> {
-        let mut file = fs::File::open(&self.info_file)?;
+        let mut file = fs::File::open(&self.info_file).in_file(&self.info_file)?;
 
         let mut info = String::new();
-        file.read_to_string(&mut info)?;
+        file.read_to_string(&mut info).in_file(&self.info_file)?;
 
         Ok(info)
     }
 
-    fn load_data(&self, file: &path::Path) -> Result<Data, Error> {
-        let input = lzw::Decoder::open(file)?;
-
-        let mut line_count = 1;
-        let data: Vec<_> = input
-            // iterate over all bytes in the input
-            .bytes()
-            // panic on error
-            .map(|c| c.unwrap())
-            // count lines and skip certain characters
-            .filter_map(|c| {
-                match c {
-                    b'\n' => {
-                        line_count += 1;
-                        None
-                    }
-                    b' ' => None,
-                    _ => Some((c, line_count))
-                }
-            })
+    fn load_file(&self, file: &path::Path) -> Result<Data, Error> {
+        let input = lzw::Decoder::open(file).in_file(file)?;
+        parse(input, file)
+    }
+}
+
+/// Parse a data file, which consists of 21 header lines followed by the samples. Each sample
+/// is 32 lines of 32 binary digits and a line with the class label.
+fn parse<R: Read>(input: R, file: &path::Path) -> Result<Data, Error> {
+    let error = |line, column, token: &[u8], expected| ParseError {
+        file: file.to_owned(),
+        line,
+        column,
+        token: String::from_utf8_lossy(token).into_owned(),
+        expected,
+    };
+
+    let mut data = Vec::new();
+    let mut line = 1;
+    let mut column = 0;
+    for c in input.bytes() {
+        let c = c?;
+        column += 1;
+        match c {
+            b'\n' => {
+                line += 1;
+                column = 0;
+            }
             // skip 21 header lines
-            .skip_while(|&(_, line)| line < 22)
-            // convert ASCII to numbers
-            .map(|(c, line)| match c {
-                b'0'...b'9' => c - b'0',
-                _ => panic!(format!("Invalid character '{}' in data file (line {})", c as char, line))
-            })
-            .collect();
+            _ if line < 22 => {}
+            b' ' => {}
+            b'0'..=b'9' => data.push(c - b'0'),
+            _ => return Err(error(line, column, &[c], "digit").into()),
+        }
+    }
+
+    if data.len() % (32 * 32 + 1) != 0 {
+        return Err(error(line, column + 1, b"", "1024 pixels and a class label per sample").into())
+    }
+
+    Ok(Data::from(data))
+}
+
+impl Loader for DataSetLoader {
+    type Data = Data;
+
+    fn load_info(&self) -> Result<String, Error> {
+        DataSetLoader::load_info(self)
+    }
+
+    fn load_data(&self) -> Result<Data, Error> {
+        DataSetLoader::load_data(self)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        DataSetLoader::verify(self)
+    }
+
+    fn has_official_split(&self) -> bool {
+        true
+    }
 
-        Ok(Data::from(data))
+    fn load_split(&self, split: Split) -> Result<Data, Error> {
+        DataSetLoader::load_split(self, split)
     }
 }
 
@@ -143,6 +284,11 @@ impl Data {
         }
     }
 
+    fn append(&mut self, other: Data) {
+        self.data.extend(other.data);
+        self.n_samples += other.n_samples;
+    }
+
     pub fn n_samples(&self) -> usize {
         self.n_samples
     }
@@ -158,17 +304,70 @@ impl Data {
     }
 }
 
-impl CanonicalData for Data {
-    fn to_canonical(&self) -> (Array2<f64>, Array2<f64>) {
-        let x8 = ArrayView2::from_shape((self.n_samples, 1024).strides((1025, 1)), &self.data).unwrap();
-        let y8 = ArrayView2::from_shape((self.n_samples, 1).strides((1025, 1)), &self.data[1024..]).unwrap();
+impl<T: Copy + Default + From<u8>> CanonicalSamples<T> for Data {
+    fn canonical_shape(&self) -> (usize, usize, usize) {
+        (self.n_samples, 1024, 1)
+    }
 
-        let mut x = Array2::zeros((self.n_samples, 1024));
-        let mut y = Array2::zeros((self.n_samples, 1));
+    fn write_canonical(&self, idx: usize, mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>) {
+        let (xi, yi) = self.get_sample(idx);
+        Zip::from(&mut x).and(xi.into_shape(1024).unwrap()).apply(|out, &inp| *out = T::from(inp));
+        y[0] = T::from(yi);
+    }
+}
+
+impl Dataset for Data {
+    const NAME: &'static str = "uci/optdigits";
+
+    type Features<'a> = ArrayView2<'a, u8>;
+    type Target = u8;
+    type Loader = DataSetLoader;
+
+    fn loader() -> Result<DataSetLoader, Error> {
+        DataSet::new().create()
+    }
+
+    fn n_samples(&self) -> usize {
+        Data::n_samples(self)
+    }
+
+    fn get_sample(&self, idx: usize) -> (ArrayView2<u8>, u8) {
+        Data::get_sample(self, idx)
+    }
+}
+
+impl HasSchema for Data {
+    fn schema() -> Schema {
+        Schema {
+            features: pixel_columns(32, 32, 1),
+            targets: vec![
+                Column::new("digit", "handwritten digit",
+                            Kind::categorical(&["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"])),
+            ],
+        }
+    }
+}
+
+impl<T: Copy + Default + From<u8>> CanonicalDataAs<T> for Data {
+    fn to_canonical_as(&self) -> (Array2<T>, Array2<T>) {
+        let (x8, y8) = self.canonical_view();
+
+        let mut x = Array2::from_elem((self.n_samples, 1024), T::default());
+        let mut y = Array2::from_elem((self.n_samples, 1), T::default());
+
+        Zip::from(&mut x).and(&x8).apply(|out, &inp| *out = T::from(inp));
+        Zip::from(&mut y).and(&y8).apply(|out, &inp| *out = T::from(inp));
+
+        (x, y)
+    }
+}
 
-        Zip::from(&mut x).and(&x8).apply(|out, &inp| *out = inp as f64);
-        Zip::from(&mut y).and(&y8).apply(|out, &inp| *out = inp as f64);
+impl CanonicalView for Data {
+    type Elem = u8;
 
+    fn canonical_view(&self) -> (ArrayView2<u8>, ArrayView2<u8>) {
+        let x = ArrayView2::from_shape((self.n_samples, 1024).strides((1025, 1)), &self.data).unwrap();
+        let y = ArrayView2::from_shape((self.n_samples, 1).strides((1025, 1)), &self.data[1024..]).unwrap();
         (x, y)
     }
 }
@@ -178,16 +377,20 @@ impl CanonicalData for Data {
 mod tests {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
+    use canonical::CanonicalData;
     use super::*;
 
     #[test]
     fn load() {
         let data = DataSet::new().download(false).create().unwrap();
-        let tst = data.load_testing_data().unwrap();
-        assert_eq!(tst.n_samples, 946);
+        let val = data.load_validation_data().unwrap();
+        assert_eq!(val.n_samples, 946);
         // check class labels of a few specific samples
-        assert_eq!(tst.get_sample(1).1, 6);
-        assert_eq!(tst.get_sample(945).1, 5);
+        assert_eq!(val.get_sample(1).1, 6);
+        assert_eq!(val.get_sample(945).1, 5);
+
+        let tst = data.load_testing_data().unwrap();
+        assert_eq!(tst.n_samples, 1797);
 
 
         let tra = data.load_training_data().unwrap();
@@ -195,6 +398,15 @@ mod tests {
         // check class labels of a few specific samples
         assert_eq!(tra.get_sample(1).1, 0);
         assert_eq!(tra.get_sample(1933).1, 8);
+
+        let all = data.load_data().unwrap();
+        assert_eq!(all.n_samples, 1934 + 946 + 1797);
+        assert_eq!(all.get_sample(1).1, 0);
+        assert_eq!(all.get_sample(1934 + 1).1, 6);
+
+        assert_eq!(data.load_split(Split::Train).unwrap().n_samples, 1934);
+        assert_eq!(data.load_split(Split::Validation).unwrap().n_samples, 946);
+        assert_eq!(data.load_split(Split::Test).unwrap().n_samples, 1797);
     }
 
     fn checksum<'a, I: Iterator<Item=&'a f64>>(iter: I) -> u64 {
@@ -208,19 +420,53 @@ mod tests {
     #[test]
     fn canonical() {
         let data = DataSet::new().download(true).create().unwrap();
+        let (x_val, y_val) = data.load_validation_data().unwrap().into_canonical();
+        assert_eq!(x_val.shape(), [946, 32 * 32]);
+        assert_eq!(y_val.shape(), [946, 1]);
+        assert_eq!(checksum(x_val.slice(s![42, ..]).iter()), 0xe65eee8be853c419);
+        assert_eq!(y_val[[1, 0]], 6.0);
+        assert_eq!(y_val[[945, 0]], 5.0);
+
         let (x_test, y_test) = data.load_testing_data().unwrap().into_canonical();
-        assert_eq!(x_test.shape(), [946, 32 * 32]);
-        assert_eq!(y_test.shape(), [946, 1]);
-        assert_eq!(checksum(x_test.slice(s![42, ..]).iter()), 0xe65eee8be853c419);
-        assert_eq!(y_test[[1, 0]], 6.0);
-        assert_eq!(y_test[[945, 0]], 5.0);
+        assert_eq!(x_test.shape(), [1797, 32 * 32]);
+        assert_eq!(y_test.shape(), [1797, 1]);
 
 
-        let  (x_train, y_train) = data.load_training_data().unwrap().into_canonical();
+        let (x_train, y_train) = data.load_training_data().unwrap().into_canonical();
         assert_eq!(x_train.shape(), [1934, 32 * 32]);
         assert_eq!(y_train.shape(), [1934, 1]);
         assert_eq!(checksum(x_train.slice(s![42, ..]).iter()), 0xb75cb4f44968156d);
         assert_eq!(y_train[[1, 0]], 0.0);
         assert_eq!(y_train[[1933, 0]], 8.0);
     }
+
+    #[test]
+    fn view() {
+        let data = DataSet::new().download(true).create().unwrap().load_testing_data().unwrap();
+        let (x, y): (Array2<u8>, Array2<u8>) = data.to_canonical_as();
+        let (xv, yv) = data.canonical_view();
+        assert_eq!(xv, x);
+        assert_eq!(yv, y);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let file = path::Path::new("optdigits-orig.tes.Z");
+        let mut text = "header\n".repeat(21);
+        text += &"0".repeat(32 * 32);
+        text += " 7\n";
+
+        let data = parse(text.as_bytes(), file).unwrap();
+        assert_eq!(data.get_sample(0).1, 7);
+
+        match parse(text.replace(" 7", " x").as_bytes(), file) {
+            Err(Error::Parse(e)) => assert_eq!((e.line, e.column, e.token.as_str()), (22, 1026, "x")),
+            _ => panic!("expected parse error"),
+        }
+
+        match parse(&text.as_bytes()[..text.len() - 3], file) {
+            Err(Error::Parse(e)) => assert_eq!((e.line, e.token.as_str()), (22, "")),
+            _ => panic!("expected parse error"),
+        }
+    }
 }
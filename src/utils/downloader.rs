@@ -1,26 +1,893 @@
 //! Functions for downloading
 
+use std::env;
 use std::fs;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use fs2::{self, FileExt};
 
 use reqwest;
+use reqwest::header::{ByteRangeSpec, ContentLength, ContentRange, ContentRangeSpec, Range};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+use utils::error::{Context, Error};
+
+/// Implement the download options of a `DataSet` builder by delegating to its `downloader` field.
+///
+/// Expands to the methods `mirror`, `offline` and `progress` inside the `impl` block of the
+/// builder, so that all data sets offer the same options.
+macro_rules! downloader_options {
+    () => {
+        /// Try the mirror with given base URL before the default download locations.
+        ///
+        /// The mirror must provide the files under the same names as in the data directory.
+        pub fn mirror<S: Into<String>>(&mut self, base_url: S) -> &mut Self {
+            self.downloader.mirror(base_url);
+            self
+        }
+
+        /// Never download anything; fail with `Error::NotCached` if files are missing.
+        pub fn offline(&mut self, b: bool) -> &mut Self {
+            self.downloader.offline(b);
+            self
+        }
+
+        /// Report the progress of downloads to `f`.
+        pub fn progress<F: Fn($crate::utils::downloader::Progress) + Send + Sync + 'static>(&mut self, f: F) -> &mut Self {
+            self.downloader.progress(f);
+            self
+        }
+    };
+}
+
+/// A file that is downloaded from a remote location.
+///
+/// The expected size and SHA-256 digest are used to verify the integrity of the file. If they are
+/// not known, any non-empty file is accepted.
+#[derive(Debug, Copy, Clone)]
+pub struct RemoteFile {
+    /// File name in the local data directory
+    pub name: &'static str,
+
+    /// Where to download the file from, in order of preference
+    pub urls: &'static [&'static str],
+
+    /// Expected SHA-256 digest as lower case hex string
+    pub sha256: Option<&'static str>,
+
+    /// Expected size in bytes
+    pub size: Option<u64>,
+}
+
+impl RemoteFile {
+    /// Check that a local copy of the file is complete and unmodified.
+    pub fn verify<P: AsRef<path::Path>>(&self, file: P) -> Result<(), Error> {
+        let file = file.as_ref();
+        let size = fs::metadata(file).in_file(file)?.len();
 
-use utils::error::Error;
+        if size == 0 {
+            return Err(Error::Integrity {
+                path: file.to_owned(),
+                expected: "a non-empty file".to_owned(),
+                found: "an empty file".to_owned(),
+            })
+        }
 
-/// Make sure a file exists by downloading from given URL if necessary.
-pub fn assure_file<P: AsRef<path::Path>, U: reqwest::IntoUrl>(file: P, url: U) -> Result<(), Error> {
-    let file = file.as_ref();
+        if let Some(expected) = self.size {
+            if size != expected {
+                return Err(Error::Integrity {
+                    path: file.to_owned(),
+                    expected: format!("{} bytes", expected),
+                    found: format!("{} bytes", size),
+                })
+            }
+        }
+
+        if let Some(expected) = self.sha256 {
+            let digest = sha256(file).in_file(file)?;
+            if digest != expected {
+                return Err(Error::Integrity {
+                    path: file.to_owned(),
+                    expected: format!("SHA-256 {}", expected),
+                    found: format!("SHA-256 {}", digest),
+                })
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// SHA-256 digest of a file as lower case hex string.
+pub fn sha256<P: AsRef<path::Path>>(file: P) -> io::Result<String> {
+    let mut input = fs::File::open(file)?;
+    let mut hasher = Sha256::default();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let n = input.read(&mut buffer)?;
+        if n == 0 {
+            break
+        }
+        hasher.input(&buffer[..n]);
+    }
+    Ok(hasher.result().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Progress of a download, as reported to progress callbacks.
+#[derive(Debug, Copy, Clone)]
+pub struct Progress<'a> {
+    /// Name of the file that is being downloaded
+    pub name: &'a str,
+
+    /// Number of bytes downloaded so far
+    pub done: u64,
+
+    /// Total size of the file in bytes, if known
+    pub total: Option<u64>,
+}
+
+/// Environment variable with mirrors that are tried before the URLs declared by the data sets.
+///
+/// Mirrors are base URLs separated by whitespace or commas. A file is requested from
+/// `<mirror>/<file name>`, where the file name is the name of the file in the data directory
+/// (e.g. `mnist-original.hdf5`).
+pub const MIRRORS_VAR: &str = "MLDATA_MIRRORS";
+
+/// Environment variable that enables offline mode if set to anything but `0`, `false` or an
+/// empty string.
+pub const OFFLINE_VAR: &str = "MLDATA_OFFLINE";
+
+/// Configure how files are downloaded.
+///
+/// This structure implements the builder pattern. It is usually configured through the
+/// `DataSet` builders of the data set modules.
+#[derive(Clone)]
+pub struct Downloader {
+    progress: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+    retries: usize,
+    mirrors: Vec<String>,
+    offline: bool,
+    lock_timeout: Duration,
+}
+
+impl Default for Downloader {
+    /// Default settings, including the mirrors from the `MLDATA_MIRRORS` and offline mode from
+    /// the `MLDATA_OFFLINE` environment variables.
+    fn default() -> Self {
+        Downloader {
+            progress: None,
+            retries: 3,
+            mirrors: env::var(MIRRORS_VAR).map(|m| parse_mirrors(&m)).unwrap_or_default(),
+            offline: env::var(OFFLINE_VAR).map(|v| parse_flag(&v)).unwrap_or(false),
+            lock_timeout: LOCK_TIMEOUT,
+        }
+    }
+}
+
+impl Downloader {
+    pub fn new() -> Self {
+        Downloader::default()
+    }
+
+    /// Call `f` whenever a chunk of data has been downloaded.
+    pub fn progress<F: Fn(Progress) + Send + Sync + 'static>(&mut self, f: F) -> &mut Self {
+        self.progress = Some(Arc::new(f));
+        self
+    }
+
+    /// How often an interrupted download is resumed before giving up (default: 3).
+    pub fn retries(&mut self, n: usize) -> &mut Self {
+        self.retries = n;
+        self
+    }
+
+    /// Try the mirror with given base URL before the URLs declared by the data set.
+    ///
+    /// Mirrors are tried in the order they are added, after any mirrors from the
+    /// `MLDATA_MIRRORS` environment variable.
+    pub fn mirror<S: Into<String>>(&mut self, base_url: S) -> &mut Self {
+        self.mirrors.push(base_url.into());
+        self
+    }
+
+    /// Never download anything.
+    ///
+    /// In offline mode, files that are not in the data directory cause an `Error::NotCached`
+    /// that names the files and where to download them from.
+    pub fn offline(&mut self, b: bool) -> &mut Self {
+        self.offline = b;
+        self
+    }
+
+    /// How long to wait for another process that downloads the same file (default: 5 minutes).
+    ///
+    /// The timeout starts over whenever the other process makes progress, so it only expires if
+    /// that process hangs. Then `Error::Locked` is returned.
+    pub fn lock_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Make sure valid copies of several remote files exist, downloading them if necessary.
+    ///
+    /// In offline mode, the error lists all files that are missing.
+    pub fn assure_files(&self, files: &[(&path::Path, &RemoteFile)]) -> Result<(), Error> {
+        if self.offline {
+            let missing: Vec<_> = files.iter()
+                .filter(|&&(file, remote)| remote.verify(file).is_err())
+                .map(|&(file, remote)| (file.to_owned(), self.urls(remote)))
+                .collect();
+            if !missing.is_empty() {
+                return Err(Error::NotCached(missing))
+            }
+            return Ok(())
+        }
+
+        for &(file, remote) in files {
+            self.assure_file(file, remote)?;
+        }
+        Ok(())
+    }
 
-    if !file.exists() {
-        let mut file = fs::File::create(file)?;
+    /// All URLs of a remote file, in the order they are tried.
+    pub fn urls(&self, remote: &RemoteFile) -> Vec<String> {
+        self.mirrors.iter()
+            .map(|m| format!("{}/{}", m.trim_end_matches('/'), remote.name))
+            .chain(remote.urls.iter().map(|&u| u.to_owned()))
+            .collect()
+    }
+
+    /// Make sure a valid copy of a remote file exists, downloading it if necessary.
+    ///
+    /// Existing files that fail verification are deleted and downloaded again. If the downloaded
+    /// file fails verification too, `Error::Integrity` is returned.
+    ///
+    /// The file is streamed into a temporary file next to it, which is renamed after successful
+    /// verification. Thus, the file is either complete or does not exist at all. Concurrent
+    /// downloads of the same file, e.g. by processes sharing the same data directory, are
+    /// serialized with a lock file (see [`lock_timeout`](#method.lock_timeout)).
+    ///
+    /// Interrupted downloads are resumed with HTTP range requests, both immediately and in later
+    /// calls, because the temporary file is kept. If the server does not support range requests,
+    /// the file is downloaded from the start.
+    ///
+    /// The mirrors and URLs of the file are tried in turn until the download succeeds. If there
+    /// are none, `Error::NoUrl` is returned.
+    pub fn assure_file<P: AsRef<path::Path>>(&self, file: P, remote: &RemoteFile) -> Result<(), Error> {
+        let file = file.as_ref();
+
+        if file.exists() && remote.verify(file).is_ok() {
+            return Ok(())
+        }
+
+        let urls = self.urls(remote);
+        if self.offline {
+            return Err(Error::NotCached(vec![(file.to_owned(), urls)]))
+        }
+        if urls.is_empty() {
+            return Err(Error::NoUrl(remote.name.to_owned()))
+        }
+
+        let part = with_suffix(file, ".part");
+        let _lock = LockFile::acquire(&with_suffix(file, ".lock"), &part, self.lock_timeout)?;
+
+        // another process may have completed the download while we were waiting for the lock
+        if file.exists() {
+            if remote.verify(file).is_ok() {
+                return Ok(())
+            }
+            fs::remove_file(file).in_file(file)?;
+        }
+
+        let mut result = Err(Error::NoUrl(remote.name.to_owned()));
+        for url in urls {
+            result = self.download_with_retries(&part, remote, &url);
+            if result.is_ok() {
+                break
+            }
+        }
+        result.in_file(&part)?;
+
+        if let Err(e) = remote.verify(&part) {
+            let _ = fs::remove_file(&part);
+            return Err(e)
+        }
+
+        fs::rename(&part, file).in_file(file)
+    }
+
+    fn download_with_retries(&self, file: &path::Path, remote: &RemoteFile, url: &str) -> Result<(), Error> {
+        let mut attempt = 0;
+        loop {
+            match self.download(file, remote, url) {
+                Err(ref e) if attempt < self.retries && is_interruption(e) => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Download a file, or the part of it that is missing in an existing partial download.
+    fn download(&self, file: &path::Path, remote: &RemoteFile, url: &str) -> Result<(), Error> {
+        let offset = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+
+        let client = reqwest::Client::new();
+        let mut request = client.get(url);
+        if offset > 0 {
+            request.header(Range::Bytes(vec![ByteRangeSpec::AllFrom(offset)]));
+        }
+        let response = request.send()?;
+
+        let length = response.headers().get::<ContentLength>().map(|&ContentLength(n)| n);
+        let range = match response.headers().get::<ContentRange>() {
+            Some(&ContentRange(ContentRangeSpec::Bytes { range, instance_length })) => Some((range, instance_length)),
+            _ => None,
+        };
+
+        let (content, output, done, total) = match (response.status(), range) {
+            (StatusCode::PartialContent, Some((Some((start, _)), total))) if offset > 0 && start == offset => {
+                let output = fs::OpenOptions::new().append(true).open(file)?;
+                (response, output, offset, total.or(length.map(|n| offset + n)))
+            }
+            (StatusCode::RangeNotSatisfiable, Some((None, Some(total)))) if offset > 0 && total == offset => {
+                // the partial download is already complete
+                return Ok(())
+            }
+            (StatusCode::RangeNotSatisfiable, _) | (StatusCode::PartialContent, _) if offset > 0 => {
+                // the partial download does not match the remote file, or the server sent a
+                // different range than requested; start over
+                fs::remove_file(file)?;
+                return self.download(file, remote, url)
+            }
+            (StatusCode::PartialContent, _) => {
+                return Err(Error::Integrity {
+                    path: file.to_owned(),
+                    expected: "the complete file".to_owned(),
+                    found: "partial content without a range request".to_owned(),
+                })
+            }
+            _ => {
+                // the server ignored the range request or no partial download exists
+                let response = response.error_for_status()?;
+                (response, fs::File::create(file)?, 0, length)
+            }
+        };
+
+        self.stream(content, output, file, remote, done, total)
+    }
+
+    fn stream(&self, mut content: reqwest::Response, mut output: fs::File, file: &path::Path, remote: &RemoteFile, mut done: u64, total: Option<u64>) -> Result<(), Error> {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        self.report(remote, done, total);
+        loop {
+            let n = content.read(&mut buffer)?;
+            if n == 0 {
+                break
+            }
+            output.write_all(&buffer[..n])?;
+            done += n as u64;
+            self.report(remote, done, total);
+        }
+        output.sync_all()?;
+
+        if let Some(expected) = total {
+            if done != expected {
+                return Err(Error::Integrity {
+                    path: file.to_owned(),
+                    expected: format!("{} bytes (Content-Length)", expected),
+                    found: format!("{} bytes", done),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report(&self, remote: &RemoteFile, done: u64, total: Option<u64>) {
+        if let Some(ref f) = self.progress {
+            f(Progress { name: remote.name, done, total });
+        }
+    }
+}
+
+/// Make sure a valid copy of a remote file exists, downloading it with default settings if
+/// necessary.
+///
+/// See [`Downloader::assure_file`](struct.Downloader.html#method.assure_file).
+pub fn assure_file<P: AsRef<path::Path>>(file: P, remote: &RemoteFile) -> Result<(), Error> {
+    Downloader::new().assure_file(file, remote)
+}
+
+/// Split the value of the `MLDATA_MIRRORS` environment variable into base URLs.
+fn parse_mirrors(value: &str) -> Vec<String> {
+    value.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|m| !m.is_empty())
+        .map(|m| m.to_owned())
+        .collect()
+}
+
+/// Interpret the value of a boolean environment variable.
+fn parse_flag(value: &str) -> bool {
+    match value.trim() {
+        "" | "0" => false,
+        v => !v.eq_ignore_ascii_case("false"),
+    }
+}
+
+/// Whether a failed download was interrupted and may be resumed, as opposed to being rejected by
+/// the server.
+fn is_interruption(err: &Error) -> bool {
+    match *err {
+        Error::Io(_) | Error::Integrity { .. } => true,
+        _ => false,
+    }
+}
+
+/// Path of a file with a suffix appended to the file name.
+fn with_suffix(file: &path::Path, suffix: &str) -> path::PathBuf {
+    let mut name = file.file_name().map(|n| n.to_owned()).unwrap_or_default();
+    name.push(suffix);
+    file.with_file_name(name)
+}
+
+/// Size of the chunks in which files are downloaded
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long to wait for a lock held by another process that makes no progress downloading
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Exclusive advisory lock on a lock file.
+///
+/// The operating system releases the lock when the process exits, so lock files left over from
+/// crashed processes do not block anyone. The lock file is deleted when this structure is dropped.
+#[derive(Debug)]
+pub struct LockFile {
+    path: path::PathBuf,
+    file: fs::File,
+}
+
+impl LockFile {
+    /// Lock the lock file, waiting for other processes to release it.
+    ///
+    /// Returns `Error::Locked` if the lock is not released within `timeout`. The timeout starts
+    /// over whenever the file `progress`, which the other process writes to, changes its size.
+    pub fn acquire(path: &path::Path, progress: &path::Path, timeout: Duration) -> Result<LockFile, Error> {
+        let mut size = fs::metadata(progress).map(|m| m.len()).ok();
+        let mut since = Instant::now();
+        loop {
+            if let Some(lock) = LockFile::try_acquire(path)? {
+                return Ok(lock)
+            }
+
+            let current = fs::metadata(progress).map(|m| m.len()).ok();
+            if current != size {
+                size = current;
+                since = Instant::now();
+            } else if since.elapsed() > timeout {
+                return Err(Error::Locked(path.to_owned()))
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
 
-        let mut content = reqwest::get(url)?;
-        let mut data = Vec::new();
-        content.read_to_end(&mut data)?;
+    /// Lock the lock file if no other process holds the lock.
+    pub fn try_acquire(path: &path::Path) -> Result<Option<LockFile>, Error> {
+        loop {
+            let file = fs::OpenOptions::new().write(true).create(true).truncate(false).open(path).in_file(path)?;
+            match file.try_lock_exclusive() {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == fs2::lock_contended_error().kind() => return Ok(None),
+                Err(e) => return Err(Error::from(e).in_file(path)),
+            }
 
-        file.write_all(&data)?;
+            // the previous holder deletes the file before releasing the lock, so we may have
+            // locked a file that no longer exists under this path
+            if is_same_file(&file, path) {
+                return Ok(Some(LockFile { path: path.to_owned(), file }))
+            }
+        }
     }
 
-    Ok(())
+    /// Whether another process holds the lock.
+    pub fn is_locked(path: &path::Path) -> Result<bool, Error> {
+        if !path.exists() {
+            return Ok(false)
+        }
+        Ok(LockFile::try_acquire(path)?.is_none())
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        // delete while still locked; the lock is released when the file is closed
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(file: &fs::File, path: &path::Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_file: &fs::File, path: &path::Path) -> bool {
+    // files that are open cannot be deleted on other platforms
+    path.exists()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use utils::temp::TempDir;
+    use super::*;
+
+    /// Serve one response per connection on a local port and return the URL.
+    ///
+    /// The thread returns the requests it received.
+    fn serve(responses: Vec<Vec<u8>>) -> (&'static str, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/data", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = String::new();
+                let mut reader = io::BufReader::new(stream.try_clone().unwrap());
+                while reader.read_line(&mut request).unwrap() > 2 {}
+                requests.push(request);
+                let _ = stream.write_all(&response);
+            }
+            requests
+        });
+
+        (Box::leak(url.into_boxed_str()), handle)
+    }
+
+    fn response(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\n{}Connection: close\r\n\r\n", status, headers).into_bytes();
+        response.extend(body);
+        response
+    }
+
+    fn remote(name: &'static str, url: &'static str) -> RemoteFile {
+        RemoteFile {
+            name,
+            urls: Box::leak(vec![url].into_boxed_slice()),
+            sha256: None,
+            size: None,
+        }
+    }
+
+    fn content(n: usize) -> Vec<u8> {
+        (0..n).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn verify() {
+        let dir = TempDir::new("downloader");
+        let file = dir.join("mldata-verify-test.txt");
+        fs::File::create(&file).unwrap().write_all(b"abc").unwrap();
+
+        let mut remote = RemoteFile {
+            name: "mldata-verify-test.txt",
+            urls: &[],
+            sha256: Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            size: Some(3),
+        };
+        remote.verify(&file).unwrap();
+
+        remote.size = Some(4);
+        match remote.verify(&file) {
+            Err(Error::Integrity { ref found, .. }) => assert_eq!(found, "3 bytes"),
+            _ => panic!("expected integrity error"),
+        }
+
+        remote.size = None;
+        remote.sha256 = Some("0000000000000000000000000000000000000000000000000000000000000000");
+        assert!(remote.verify(&file).is_err());
+
+        fs::File::create(&file).unwrap();
+        remote.sha256 = None;
+        assert!(remote.verify(&file).is_err());
+    }
+
+    #[test]
+    fn lock() {
+        let dir = TempDir::new("downloader");
+        let path = dir.join("mldata-lock-test.lock");
+        let progress = dir.join("mldata-lock-test.part");
+
+        let lock = LockFile::acquire(&path, &progress, Duration::from_secs(10)).unwrap();
+        assert!(LockFile::is_locked(&path).unwrap());
+        let waiter = {
+            let (path, progress) = (path.clone(), progress.clone());
+            thread::spawn(move || LockFile::acquire(&path, &progress, Duration::from_secs(10)).map(|_| ()))
+        };
+        thread::sleep(Duration::from_millis(300));
+        assert!(path.exists());
+        drop(lock);
+
+        waiter.join().unwrap().unwrap();
+        assert!(!path.exists());
+        assert!(!LockFile::is_locked(&path).unwrap());
+
+        // lock files without a process holding the lock are left over from crashes
+        fs::File::create(&path).unwrap();
+        assert!(!LockFile::is_locked(&path).unwrap());
+        drop(LockFile::acquire(&path, &progress, Duration::from_secs(0)).unwrap());
+    }
+
+    #[test]
+    fn lock_timeout() {
+        let dir = TempDir::new("downloader");
+        let path = dir.join("mldata-lock-timeout-test.lock");
+        let progress = dir.join("mldata-lock-timeout-test.part");
+
+        let _lock = LockFile::try_acquire(&path).unwrap().unwrap();
+        assert!(LockFile::try_acquire(&path).unwrap().is_none());
+
+        let start = Instant::now();
+        match LockFile::acquire(&path, &progress, Duration::from_millis(300)) {
+            Err(Error::Locked(ref p)) => assert_eq!(p, &path),
+            _ => panic!("expected locked error"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // the timeout starts over while the holder of the lock makes progress
+        let writer = {
+            let progress = progress.clone();
+            thread::spawn(move || {
+                let mut file = fs::File::create(&progress).unwrap();
+                for _ in 0..8 {
+                    thread::sleep(Duration::from_millis(150));
+                    file.write_all(b"x").unwrap();
+                }
+            })
+        };
+        let start = Instant::now();
+        assert!(LockFile::acquire(&path, &progress, Duration::from_millis(400)).is_err());
+        assert!(start.elapsed() > Duration::from_millis(1200));
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn replace_corrupted() {
+        let dir = TempDir::new("downloader");
+        let body = content(1000);
+        let (url, server) = serve(vec![response("200 OK", "Content-Length: 1000\r\n", &body)]);
+
+        let file = dir.join("mldata-corrupted-test.bin");
+        let mut corrupted = body.clone();
+        corrupted[500] ^= 0xff;
+        fs::File::create(&file).unwrap().write_all(&corrupted).unwrap();
+
+        let mut remote = remote("mldata-corrupted-test.bin", url);
+        remote.size = Some(1000);
+        remote.sha256 = Some(Box::leak(format!("{:x}", Sha256::digest(&body)).into_boxed_str()));
+        assert!(remote.verify(&file).is_err());
+
+        assure_file(&file, &remote).unwrap();
+        server.join().unwrap();
+        assert_eq!(fs::read(&file).unwrap(), body);
+        remote.verify(&file).unwrap();
+    }
+
+    #[test]
+    fn failed_download() {
+        let dir = TempDir::new("downloader");
+        let file = dir.join("mldata-failed-download.txt");
+        let remote = RemoteFile {
+            name: "mldata-failed-download.txt",
+            urls: &["http://127.0.0.1:1/unreachable", "http://127.0.0.1:1/also-unreachable"],
+            sha256: None,
+            size: None,
+        };
+
+        assert!(assure_file(&file, &remote).is_err());
+        assert!(!file.exists());
+        assert!(!with_suffix(&file, ".part").exists());
+        assert!(!with_suffix(&file, ".lock").exists());
+    }
+
+    #[test]
+    fn streaming_with_progress() {
+        let dir = TempDir::new("downloader");
+        let body = content(200_000);
+        let (url, server) = serve(vec![response("200 OK", "Content-Length: 200000\r\n", &body)]);
+
+        let file = dir.join("mldata-streaming-test.bin");
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut downloader = Downloader::new();
+        {
+            let reports = reports.clone();
+            downloader.progress(move |p| reports.lock().unwrap().push((p.name.to_owned(), p.done, p.total)));
+        }
+
+        downloader.assure_file(&file, &remote("mldata-streaming-test.bin", url)).unwrap();
+        server.join().unwrap();
+        assert_eq!(fs::read(&file).unwrap(), body);
+
+        let reports = reports.lock().unwrap();
+        assert!(reports.len() > 2);
+        assert_eq!(reports[0], ("mldata-streaming-test.bin".to_owned(), 0, Some(200_000)));
+        assert_eq!(reports[reports.len() - 1].1, 200_000);
+        assert!(reports.windows(2).all(|w| w[0].1 <= w[1].1));
+    }
+
+    #[test]
+    fn keep_partial_download() {
+        let dir = TempDir::new("downloader");
+        let body = content(1000);
+        let (url, server) = serve(vec![response("200 OK", "Content-Length: 1000\r\n", &body[..500])]);
+
+        let file = dir.join("mldata-partial-test.bin");
+        let part = with_suffix(&file, ".part");
+
+        assert!(Downloader::new().retries(0).assure_file(&file, &remote("mldata-partial-test.bin", url)).is_err());
+        server.join().unwrap();
+        assert!(!file.exists());
+        assert_eq!(fs::metadata(&part).unwrap().len(), 500);
+
+        // a later call resumes the download
+        let (url, server) = serve(vec![response("206 Partial Content", "Content-Range: bytes 500-999/1000\r\nContent-Length: 500\r\n", &body[500..])]);
+        Downloader::new().assure_file(&file, &remote("mldata-partial-test.bin", url)).unwrap();
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("Range: bytes=500-"), "{}", requests[0]);
+        assert_eq!(fs::read(&file).unwrap(), body);
+        assert!(!part.exists());
+    }
+
+    #[test]
+    fn resume_dropped_connection() {
+        let dir = TempDir::new("downloader");
+        let body = content(300_000);
+        let (url, server) = serve(vec![
+            response("200 OK", "Content-Length: 300000\r\n", &body[..100_000]),
+            response("206 Partial Content", "Content-Range: bytes 100000-299999/300000\r\nContent-Length: 200000\r\n", &body[100_000..150_000]),
+            response("206 Partial Content", "Content-Range: bytes 150000-299999/300000\r\nContent-Length: 150000\r\n", &body[150_000..]),
+        ]);
+
+        let file = dir.join("mldata-resume-test.bin");
+
+        let done = Arc::new(Mutex::new(Vec::new()));
+        let mut downloader = Downloader::new();
+        {
+            let done = done.clone();
+            downloader.progress(move |p| done.lock().unwrap().push((p.done, p.total)));
+        }
+        downloader.assure_file(&file, &remote("mldata-resume-test.bin", url)).unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("Range"));
+        assert!(requests[1].contains("Range: bytes=100000-"));
+        assert!(requests[2].contains("Range: bytes=150000-"));
+        assert_eq!(fs::read(&file).unwrap(), body);
+        assert_eq!(done.lock().unwrap().last(), Some(&(300_000, Some(300_000))));
+    }
+
+    #[test]
+    fn range_not_supported() {
+        let dir = TempDir::new("downloader");
+        let body = content(1000);
+        let (url, server) = serve(vec![
+            response("200 OK", "Content-Length: 1000\r\n", &body[..500]),
+            response("200 OK", "Content-Length: 1000\r\n", &body),
+        ]);
+
+        let file = dir.join("mldata-no-range-test.bin");
+
+        assure_file(&file, &remote("mldata-no-range-test.bin", url)).unwrap();
+        let requests = server.join().unwrap();
+        assert!(requests[1].contains("Range: bytes=500-"));
+        assert_eq!(fs::read(&file).unwrap(), body);
+    }
+
+    #[test]
+    fn wrong_range() {
+        let dir = TempDir::new("downloader");
+        let body = content(1000);
+        let (url, server) = serve(vec![
+            response("200 OK", "Content-Length: 1000\r\n", &body[..500]),
+            response("206 Partial Content", "Content-Range: bytes 400-999/1000\r\nContent-Length: 600\r\n", &body[400..]),
+            response("200 OK", "Content-Length: 1000\r\n", &body[..500]),
+            response("206 Partial Content", "Content-Length: 500\r\n", &body[500..]),
+            response("200 OK", "Content-Length: 1000\r\n", &body),
+        ]);
+
+        let file = dir.join("mldata-wrong-range-test.bin");
+
+        assure_file(&file, &remote("mldata-wrong-range-test.bin", url)).unwrap();
+
+        // partial content that does not start at the requested offset is discarded
+        let requests = server.join().unwrap();
+        assert!(requests[1].contains("Range: bytes=500-"), "{}", requests[1]);
+        assert!(!requests[2].contains("Range"), "{}", requests[2]);
+        assert!(requests[3].contains("Range: bytes=500-"), "{}", requests[3]);
+        assert!(!requests[4].contains("Range"), "{}", requests[4]);
+        assert_eq!(fs::read(&file).unwrap(), body);
+    }
+
+    #[test]
+    fn mirrors() {
+        let dir = TempDir::new("downloader");
+        assert_eq!(parse_mirrors(" http://a.example/x, http://b.example/\thttp://c.example "),
+                   ["http://a.example/x", "http://b.example/", "http://c.example"]);
+
+        let body = content(100);
+        let (missing, missing_server) = serve(vec![response("404 Not Found", "Content-Length: 0\r\n", b"")]);
+        let (mirror, mirror_server) = serve(vec![response("200 OK", "Content-Length: 100\r\n", &body)]);
+
+        let file = dir.join("mldata-mirror-test.bin");
+
+        let mut downloader = Downloader::new();
+        downloader.mirror(missing).mirror(format!("{}/", mirror));
+
+        let remote = remote("mldata-mirror-test.bin", "http://127.0.0.1:1/declared");
+        assert_eq!(downloader.urls(&remote)[1], format!("{}/mldata-mirror-test.bin", mirror));
+
+        downloader.assure_file(&file, &remote).unwrap();
+        assert!(missing_server.join().unwrap()[0].starts_with("GET /data/mldata-mirror-test.bin "));
+        assert!(mirror_server.join().unwrap()[0].starts_with("GET /data/mldata-mirror-test.bin "));
+        assert_eq!(fs::read(&file).unwrap(), body);
+    }
+
+    #[test]
+    fn no_url() {
+        let dir = TempDir::new("downloader");
+        let file = dir.join("mldata-no-url.txt");
+        let remote = RemoteFile { name: "mldata-no-url.txt", urls: &[], sha256: None, size: None };
+
+        match Downloader::new().assure_file(&file, &remote) {
+            Err(Error::NoUrl(ref name)) => assert_eq!(name, "mldata-no-url.txt"),
+            _ => panic!("expected no URL error"),
+        }
+        assert!(!with_suffix(&file, ".lock").exists());
+
+        // files in the data directory need no URL
+        fs::File::create(&file).unwrap().write_all(b"abc").unwrap();
+        Downloader::new().assure_file(&file, &remote).unwrap();
+    }
+
+    #[test]
+    fn offline() {
+        let dir = TempDir::new("downloader");
+        assert!(parse_flag("1") && parse_flag("yes"));
+        assert!(!parse_flag("") && !parse_flag("0") && !parse_flag("False"));
+
+        let cached = dir.join("mldata-offline-cached.txt");
+        fs::File::create(&cached).unwrap().write_all(b"abc").unwrap();
+        let missing = dir.join("mldata-offline-missing.txt");
+
+        let cached_remote = remote("mldata-offline-cached.txt", "http://127.0.0.1:1/cached");
+        let missing_remote = remote("mldata-offline-missing.txt", "http://127.0.0.1:1/missing");
+
+        let mut downloader = Downloader::new();
+        downloader.offline(true).mirror("http://127.0.0.1:1/mirror");
+        downloader.assure_file(&cached, &cached_remote).unwrap();
+
+        match downloader.assure_files(&[(&cached, &cached_remote), (&missing, &missing_remote)]) {
+            Err(Error::NotCached(ref files)) => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].0, missing);
+                assert_eq!(files[0].1, ["http://127.0.0.1:1/mirror/mldata-offline-missing.txt",
+                                        "http://127.0.0.1:1/missing"]);
+            }
+            _ => panic!("expected not cached error"),
+        }
+        assert!(!missing.exists());
+    }
 }
@@ -1,12 +1,17 @@
 //! Our error type.
 
+use std::error;
+use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
+use std::result;
 
 use app_dirs::AppDirsError;
 use reqwest;
 
 use ndarray::ShapeError;
 
+use utils::downloader::MIRRORS_VAR;
 use utils::hdf5;
 
 #[derive(Debug)]
@@ -15,15 +20,195 @@ pub enum Error {
     Download(reqwest::Error),
     Hdf5Error(hdf5::Error),
     ArrayError(ShapeError),
-    DataType,
-    Internal,
+
+    /// The data directory could not be determined
+    AppDirs(AppDirsError),
+
+    /// An HDF5 dataset does not have the expected element type
+    DataType {
+        path: String,
+        expected: &'static str,
+    },
+
+    Parse(ParseError),
+
+    /// A file does not have the expected size or content
+    Integrity {
+        path: PathBuf,
+        expected: String,
+        found: String,
+    },
+
+    /// No URL or mirror is known for the file with given name
+    NoUrl(String),
+
+    /// A data set is no longer available from its original source and no mirror is configured
+    NoMirror {
+        data_set: &'static str,
+        file: &'static str,
+    },
+
+    /// Another process holds the lock on the download of a file but makes no progress
+    Locked(PathBuf),
+
+    /// Files are missing from the data directory and may not be downloaded in offline mode.
+    ///
+    /// Lists the missing files and the URLs they can be downloaded from.
+    NotCached(Vec<(PathBuf, Vec<String>)>),
+
+    UnknownDataSet(String),
+    NoOfficialSplit,
+    UnknownColumn(String),
+    NotCategorical(String),
+
+    /// Error while accessing a file
+    File {
+        path: PathBuf,
+        source: Box<Error>,
+    },
+
+    /// Error while loading a data set
+    DataSet {
+        name: String,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Attach the file that was accessed when the error occurred.
+    pub fn in_file<P: AsRef<Path>>(self, path: P) -> Error {
+        Error::File {
+            path: path.as_ref().to_owned(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Attach the name of the data set that was loaded when the error occurred.
+    pub fn in_data_set<S: Into<String>>(self, name: S) -> Error {
+        Error::DataSet {
+            name: name.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The innermost error, without context.
+    pub fn root_cause(&self) -> &Error {
+        match *self {
+            Error::File { ref source, .. } | Error::DataSet { ref source, .. } => source.root_cause(),
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            // the wrapped errors are reported by `source`
+            Error::Io(_) => write!(f, "I/O error"),
+            Error::Download(_) => write!(f, "download failed"),
+            Error::Hdf5Error(_) => write!(f, "HDF5 error"),
+            Error::ArrayError(_) => write!(f, "unexpected array shape"),
+            Error::AppDirs(_) => write!(f, "cannot determine data directory"),
+            Error::DataType { ref path, expected } => write!(f, "HDF5 dataset {} is not of type {}", path, expected),
+            Error::Parse(ref e) => write!(f, "{}", e),
+            Error::Integrity { ref path, ref expected, ref found } => write!(f, "{} is corrupted: expected {}, found {}", path.display(), expected, found),
+            Error::NoUrl(ref name) => write!(f, "no URL to download {} from; set the MLDATA_MIRRORS environment variable to a mirror that provides it", name),
+            Error::NoMirror { data_set, file } => write!(f, "{} is no longer available from mldata.org; set the {} environment variable to a mirror that provides {}", data_set, MIRRORS_VAR, file),
+            Error::Locked(ref path) => write!(f, "{} is locked by another process that makes no progress; delete the lock file if that process is gone", path.display()),
+            Error::NotCached(ref files) => {
+                write!(f, "data not available in offline mode; download the following files:")?;
+                for &(ref path, ref urls) in files {
+                    write!(f, "\n  {} from {}", path.display(), urls.join(" or "))?;
+                }
+                Ok(())
+            }
+            Error::UnknownDataSet(ref name) => write!(f, "unknown data set: {}", name),
+            Error::NoOfficialSplit => write!(f, "the data set has no such official split"),
+            Error::UnknownColumn(ref name) => write!(f, "unknown column: {}", name),
+            Error::NotCategorical(ref name) => write!(f, "column {} is not categorical", name),
+            Error::File { ref path, .. } => write!(f, "error in file {}", path.display()),
+            Error::DataSet { ref name, .. } => write!(f, "error loading data set {}", name),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Download(ref e) => Some(e),
+            Error::Hdf5Error(ref e) => Some(e),
+            Error::ArrayError(ref e) => Some(e),
+            Error::AppDirs(ref e) => Some(e),
+            Error::File { ref source, .. } | Error::DataSet { ref source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
+
+/// Attach context to the error of a result.
+pub trait Context<T> {
+    /// See [`Error::in_file`](enum.Error.html#method.in_file).
+    fn in_file<P: AsRef<Path>>(self, path: P) -> Result<T, Error>;
+
+    /// See [`Error::in_data_set`](enum.Error.html#method.in_data_set).
+    fn in_data_set<S: Into<String>>(self, name: S) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for result::Result<T, E> {
+    fn in_file<P: AsRef<Path>>(self, path: P) -> Result<T, Error> {
+        self.map_err(|e| e.into().in_file(path))
+    }
+
+    fn in_data_set<S: Into<String>>(self, name: S) -> Result<T, Error> {
+        self.map_err(|e| e.into().in_data_set(name))
+    }
+}
+
+/// Invalid content in a data file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The file that failed to parse
+    pub file: PathBuf,
+
+    /// Line number, counting from 1
+    pub line: usize,
+
+    /// Position of the offending token in the line, counting from 1: the field number in delimited
+    /// files and the character position otherwise
+    pub column: usize,
+
+    /// The offending token; empty if the line ended prematurely
+    pub token: String,
+
+    /// What was expected instead
+    pub expected: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: expected {}, ", self.file.display(), self.line, self.column, self.expected)?;
+        if self.token.is_empty() {
+            write!(f, "found end of line")
+        } else {
+            write!(f, "found '{}'", self.token)
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::Parse(err)
+    }
 }
 
 impl From<AppDirsError> for Error {
     fn from(err: AppDirsError) -> Error {
         match err {
             AppDirsError::Io(e) => Error::Io(e),
-            _ => Error::Internal,
+            _ => Error::AppDirs(err),
         }
     }
 }
@@ -53,4 +238,49 @@ impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error::Io(err)
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as StdError;
+    use super::*;
+
+    #[test]
+    fn chain() {
+        let err: Result<(), _> = Err(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        let err = err.in_file("iris.data").in_data_set("uci/iris").unwrap_err();
+
+        assert_eq!(err.to_string(), "error loading data set uci/iris");
+        let source = err.source().unwrap();
+        assert_eq!(source.to_string(), "error in file iris.data");
+        let source = source.source().unwrap();
+        assert_eq!(source.to_string(), "I/O error");
+        assert_eq!(source.source().unwrap().to_string(), "no such file");
+
+        match *err.root_cause() {
+            Error::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            _ => panic!("expected I/O error"),
+        }
+
+        let boxed: Box<dyn StdError> = Box::new(err);
+        assert!(boxed.source().is_some());
+    }
+
+    #[test]
+    fn no_duplicate_messages() {
+        let err = Error::from(ParseError {
+            file: PathBuf::from("iris.data"),
+            line: 2,
+            column: 5,
+            token: "Iris-rosea".to_string(),
+            expected: "iris class",
+        });
+        assert_eq!(err.to_string(), "iris.data:2:5: expected iris class, found 'Iris-rosea'");
+        assert!(err.source().is_none());
+
+        let err = Error::from(hdf5::Error::Format("bad signature".to_string()));
+        assert_eq!(err.to_string(), "HDF5 error");
+        assert_eq!(err.source().unwrap().to_string(), "invalid HDF5 file: bad signature");
+    }
+}
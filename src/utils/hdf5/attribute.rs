@@ -0,0 +1,116 @@
+//! Attributes, i.e. small named values attached to groups and datasets
+
+use std::sync::Arc;
+
+use ndarray::{ErrorKind, ShapeError};
+
+use super::datatype::{self, Datatype};
+use super::heap::GlobalHeap;
+use super::io::{Reader, UNDEFINED};
+use super::object::{ObjectHeader, ATTRIBUTE, ATTRIBUTE_INFO};
+use super::{Dataspace, DynamicArray, Error, Result};
+
+/// A named value attached to a group or dataset.
+///
+/// The value is read together with the object it is attached to.
+#[derive(Clone)]
+pub struct Attribute {
+    reader: Arc<Reader>,
+    name: String,
+    datatype: Datatype,
+    space: Dataspace,
+    data: Vec<u8>,
+}
+
+impl Attribute {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_type(&self) -> Datatype {
+        self.datatype.clone()
+    }
+
+    pub fn get_space(&self) -> Dataspace {
+        self.space.clone()
+    }
+
+    /// The value(s) of the attribute.
+    pub fn read(&self) -> Result<DynamicArray> {
+        let mut heap = GlobalHeap::new(&self.reader);
+        datatype::decode(&self.datatype, &self.data, &self.space.shape, &mut heap)
+    }
+
+    /// The string value(s) of the attribute, in row-major order.
+    pub fn read_strings(&self) -> Result<Vec<String>> {
+        match self.read()? {
+            DynamicArray::String(strings) => Ok(strings.into_raw_vec()),
+            _ => Err(Error::UnsupportedDataType),
+        }
+    }
+
+    /// The value of an attribute that contains a single string.
+    pub fn read_string(&self) -> Result<String> {
+        let mut strings = self.read_strings()?;
+        match strings.pop() {
+            Some(s) if strings.is_empty() => Ok(s),
+            _ => Err(Error::NdError(ShapeError::from_kind(ErrorKind::IncompatibleShape))),
+        }
+    }
+}
+
+/// All attributes in an object header.
+pub fn attributes(reader: &Arc<Reader>, header: &ObjectHeader) -> Result<Vec<Attribute>> {
+    // many attributes are stored in a fractal heap instead of the object header
+    if let Some(message) = header.find(ATTRIBUTE_INFO) {
+        let mut bytes = reader.bytes(&message.data);
+        bytes.skip(1)?;
+        if bytes.u8()? & 0x01 != 0 {
+            bytes.skip(2)?;
+        }
+        if bytes.address()? != UNDEFINED {
+            return Err(Error::Unsupported("dense attribute storage".to_owned()))
+        }
+    }
+
+    header.find_all(ATTRIBUTE)
+        .map(|message| parse(reader, &message.data))
+        .collect()
+}
+
+/// Parse an attribute message.
+fn parse(reader: &Arc<Reader>, data: &[u8]) -> Result<Attribute> {
+    let mut bytes = reader.bytes(data);
+    let version = bytes.u8()?;
+    let flags = bytes.u8()?;
+    let name_size = bytes.u16()? as usize;
+    let datatype_size = bytes.u16()? as usize;
+    let dataspace_size = bytes.u16()? as usize;
+
+    // version 1 pads the name, datatype and dataspace to multiples of 8 bytes
+    let padded = |size: usize| if version == 1 { (size + 7) / 8 * 8 } else { size };
+    match version {
+        1 => {}
+        2 | 3 if flags & 0x03 != 0 => return Err(Error::Unsupported("shared attribute types".to_owned())),
+        2 => {}
+        3 => bytes.skip(1)?,  // character set of the name
+        _ => return Err(Error::Unsupported(format!("attribute message version {}", version))),
+    }
+
+    let name = bytes.take(padded(name_size))?;
+    let name = String::from_utf8_lossy(name.split(|&b| b == 0).next().unwrap_or(&[])).into_owned();
+
+    let datatype = datatype::parse(&mut reader.bytes(bytes.take(padded(datatype_size))?))?;
+    let space = Dataspace::parse(&mut reader.bytes(bytes.take(padded(dataspace_size))?))?;
+
+    let size = space.size() * datatype.size();
+    let data = bytes.take(size)?.to_owned();
+
+    Ok(Attribute {
+        reader: reader.clone(),
+        name,
+        datatype,
+        space,
+        data,
+    })
+}
@@ -0,0 +1,386 @@
+//! Element types of datasets
+
+use ndarray::{Array, IxDyn};
+
+use super::heap::GlobalHeap;
+use super::io::Bytes;
+use super::{Dataset, DynamicArray, Error, Result};
+
+/// Byte order of multi-byte values.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+/// How fixed-length strings are terminated.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StringPadding {
+    /// Terminated by a null byte unless the string takes up the full size
+    NullTerminated,
+
+    /// Padded with null bytes
+    NullPadded,
+
+    /// Padded with spaces
+    SpacePadded,
+}
+
+/// The type of the elements of a dataset or attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Datatype {
+    /// Signed or unsigned integer of 1, 2, 4 or 8 bytes
+    Integer {
+        size: usize,
+        signed: bool,
+        order: ByteOrder,
+    },
+
+    /// IEEE floating point number of 4 or 8 bytes
+    Float {
+        size: usize,
+        order: ByteOrder,
+    },
+
+    /// String of fixed size in bytes
+    FixedString {
+        size: usize,
+        padding: StringPadding,
+    },
+
+    /// String of variable length, which is stored in the global heap
+    VarString {
+        size: usize,
+    },
+
+    /// Integer with named values
+    Enum {
+        base: Box<Datatype>,
+        members: Vec<(String, i64)>,
+    },
+
+    /// Record of named fields
+    Compound {
+        size: usize,
+        fields: Vec<Field>,
+    },
+
+    /// Any other type, given by its HDF5 datatype class
+    Other {
+        class: u8,
+        size: usize,
+    },
+}
+
+/// Numeric types with a native HDF5 datatype.
+pub trait Native {
+    fn datatype() -> Datatype;
+}
+
+fn native_order() -> ByteOrder {
+    if cfg!(target_endian = "big") { ByteOrder::BigEndian } else { ByteOrder::LittleEndian }
+}
+
+macro_rules! native_integer {
+    ($($t:ty: $size:expr, $signed:expr;)*) => {
+        $(impl Native for $t {
+            fn datatype() -> Datatype {
+                Datatype::Integer { size: $size, signed: $signed, order: native_order() }
+            }
+        })*
+    }
+}
+
+native_integer! {
+    i8: 1, true;
+    i16: 2, true;
+    i32: 4, true;
+    i64: 8, true;
+    u8: 1, false;
+    u16: 2, false;
+    u32: 4, false;
+    u64: 8, false;
+}
+
+impl Native for f32 {
+    fn datatype() -> Datatype {
+        Datatype::Float { size: 4, order: native_order() }
+    }
+}
+
+impl Native for f64 {
+    fn datatype() -> Datatype {
+        Datatype::Float { size: 8, order: native_order() }
+    }
+}
+
+/// A field of a compound datatype.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+
+    /// Position of the field in the record, in bytes
+    pub offset: usize,
+
+    pub datatype: Datatype,
+}
+
+impl Datatype {
+    /// The element type of a dataset.
+    pub fn new(dset: &Dataset) -> Self {
+        dset.datatype.clone()
+    }
+
+    /// The datatype that stores `T` in the byte order of this machine.
+    pub fn native<T: Native>() -> Self {
+        T::datatype()
+    }
+
+    /// Whether elements of this type can be read as `T` without conversion.
+    ///
+    /// This replaces `equal_id(H5T_NATIVE_*)` of the former libhdf5 bindings, e.g.
+    /// `datatype.is_native::<i32>()` instead of `datatype.equal_id(H5T_NATIVE_INT32)`.
+    pub fn is_native<T: Native>(&self) -> bool {
+        *self == T::datatype()
+    }
+
+    /// Size of one element in bytes.
+    pub fn size(&self) -> usize {
+        match *self {
+            Datatype::Integer { size, .. } | Datatype::Float { size, .. } => size,
+            Datatype::FixedString { size, .. } | Datatype::VarString { size } => size,
+            Datatype::Enum { ref base, .. } => base.size(),
+            Datatype::Compound { size, .. } | Datatype::Other { size, .. } => size,
+        }
+    }
+}
+
+/// Parse a datatype message.
+///
+/// The properties of all classes are consumed, so that datatypes nested in others can be parsed.
+pub fn parse(bytes: &mut Bytes) -> Result<Datatype> {
+    let class_and_version = bytes.u8()?;
+    let flags = bytes.uint(3)?;
+    let size = bytes.u32()? as usize;
+
+    let class = class_and_version & 0x0f;
+    let version = class_and_version >> 4;
+    let order = if flags & 0x01 == 0 { ByteOrder::LittleEndian } else { ByteOrder::BigEndian };
+    let other = Datatype::Other { class, size };
+
+    Ok(match class {
+        0 => {
+            bytes.skip(4)?;
+            match size {
+                1 | 2 | 4 | 8 => Datatype::Integer {
+                    size,
+                    signed: flags & 0x08 != 0,
+                    order,
+                },
+                _ => other,
+            }
+        }
+        1 => {
+            bytes.skip(12)?;
+            // bit 6 set together with bit 0 marks the VAX byte order
+            if (size == 4 || size == 8) && flags & 0x40 == 0 {
+                Datatype::Float { size, order }
+            } else {
+                other
+            }
+        }
+        3 => Datatype::FixedString {
+            size,
+            padding: match flags & 0x0f {
+                0 => StringPadding::NullTerminated,
+                1 => StringPadding::NullPadded,
+                _ => StringPadding::SpacePadded,
+            },
+        },
+        6 => compound(bytes, version, flags as usize & 0xffff, size)?,
+        8 => enumeration(bytes, version, flags as usize & 0xffff, size)?,
+        9 => {
+            parse(bytes)?;
+            // variable-length sequences of characters, rather than of arbitrary elements
+            if flags & 0x0f == 1 {
+                Datatype::VarString { size }
+            } else {
+                other
+            }
+        }
+        10 => {
+            let rank = bytes.u8()? as usize;
+            if version < 3 {
+                bytes.skip(3 + 4 * rank)?;  // reserved and permutation
+            }
+            bytes.skip(4 * rank)?;
+            parse(bytes)?;
+            other
+        }
+        2 => {
+            bytes.skip(2)?;  // time
+            other
+        }
+        4 => {
+            bytes.skip(4)?;  // bitfield
+            other
+        }
+        5 => {
+            bytes.skip(flags as usize & 0xff)?;  // tag of opaque type
+            other
+        }
+        _ => other,
+    })
+}
+
+/// Parse the members of a compound datatype.
+fn compound(bytes: &mut Bytes, version: u8, n: usize, size: usize) -> Result<Datatype> {
+    // version 3 stores the offsets in as few bytes as the size of the record needs
+    let width = if size < 1 << 8 { 1 } else if size < 1 << 16 { 2 } else if size < 1 << 24 { 3 } else { 4 };
+
+    let mut fields = Vec::with_capacity(n);
+    for _ in 0..n {
+        let name = if version < 3 { bytes.padded_cstring(8)? } else { bytes.cstring()? };
+        let offset = if version < 3 { bytes.u32()? as usize } else { bytes.uint(width)? as usize };
+
+        // version 1 describes array fields in the member rather than with an array datatype
+        let mut dims = Vec::new();
+        if version == 1 {
+            let rank = bytes.u8()? as usize;
+            bytes.skip(11)?;
+            for d in 0..4 {
+                let n = bytes.u32()? as usize;
+                if d < rank {
+                    dims.push(n);
+                }
+            }
+        }
+
+        let mut datatype = parse(bytes)?;
+        if !dims.is_empty() {
+            datatype = Datatype::Other {
+                class: 10,
+                size: datatype.size() * dims.iter().product::<usize>(),
+            };
+        }
+        fields.push(Field { name, offset, datatype });
+    }
+    Ok(Datatype::Compound { size, fields })
+}
+
+/// Parse the base type, names and values of an enum datatype.
+fn enumeration(bytes: &mut Bytes, version: u8, n: usize, size: usize) -> Result<Datatype> {
+    let base = parse(bytes)?;
+    let mut names = Vec::with_capacity(n);
+    for _ in 0..n {
+        names.push(if version < 3 { bytes.padded_cstring(8)? } else { bytes.cstring()? });
+    }
+
+    let values = bytes.take(n * base.size())?;
+    match base {
+        Datatype::Integer { .. } => Ok(Datatype::Enum {
+            members: names.into_iter().zip(integers(&base, values)?).collect(),
+            base: Box::new(base),
+        }),
+        _ => Ok(Datatype::Other { class: 8, size }),
+    }
+}
+
+/// Convert raw elements into an array of given shape, reading variable-length elements from the
+/// global heap.
+pub fn decode(datatype: &Datatype, data: &[u8], shape: &[usize], heap: &mut GlobalHeap) -> Result<DynamicArray> {
+    let dim = IxDyn(shape);
+    Ok(match *datatype {
+        Datatype::Integer { size: 1, signed: true, order } => DynamicArray::Int8(convert(data, dim, 1, order, |x| x as i8)?),
+        Datatype::Integer { size: 2, signed: true, order } => DynamicArray::Int16(convert(data, dim, 2, order, |x| x as i16)?),
+        Datatype::Integer { size: 4, signed: true, order } => DynamicArray::Int32(convert(data, dim, 4, order, |x| x as i32)?),
+        Datatype::Integer { size: 8, signed: true, order } => DynamicArray::Int64(convert(data, dim, 8, order, |x| x as i64)?),
+        Datatype::Integer { size: 1, signed: false, order } => DynamicArray::UInt8(convert(data, dim, 1, order, |x| x as u8)?),
+        Datatype::Integer { size: 2, signed: false, order } => DynamicArray::UInt16(convert(data, dim, 2, order, |x| x as u16)?),
+        Datatype::Integer { size: 4, signed: false, order } => DynamicArray::UInt32(convert(data, dim, 4, order, |x| x as u32)?),
+        Datatype::Integer { size: 8, signed: false, order } => DynamicArray::UInt64(convert(data, dim, 8, order, |x| x)?),
+        Datatype::Float { size: 4, order } => DynamicArray::Float32(convert(data, dim, 4, order, |x| f32::from_bits(x as u32))?),
+        Datatype::Float { size: 8, order } => DynamicArray::Float64(convert(data, dim, 8, order, f64::from_bits)?),
+        Datatype::FixedString { size, padding } => {
+            let strings = data.chunks(size.max(1)).map(|s| fixed_string(s, padding)).collect();
+            DynamicArray::String(Array::from_shape_vec(dim, strings)?)
+        }
+        Datatype::VarString { size } => {
+            let strings = data.chunks(size.max(1))
+                .map(|element| Ok(String::from_utf8_lossy(heap.element(element, 1)?).into_owned()))
+                .collect::<Result<_>>()?;
+            DynamicArray::String(Array::from_shape_vec(dim, strings)?)
+        }
+        Datatype::Enum { ref base, ref members } => {
+            let values = integers(base, data)?;
+            if is_bool(members) {
+                DynamicArray::Bool(Array::from_shape_vec(dim, values.into_iter().map(|x| x != 0).collect())?)
+            } else {
+                DynamicArray::Enum {
+                    values: Array::from_shape_vec(dim, values)?,
+                    members: members.clone(),
+                }
+            }
+        }
+        Datatype::Compound { size, ref fields } => {
+            let mut columns = Vec::with_capacity(fields.len());
+            for field in fields {
+                let end = field.offset + field.datatype.size();
+                if end > size {
+                    return Err(Error::Format(format!("field {} exceeds its compound type", field.name)))
+                }
+                let column: Vec<u8> = data.chunks(size.max(1))
+                    .flat_map(|record| record[field.offset..end].iter().cloned())
+                    .collect();
+                columns.push((field.name.clone(), decode(&field.datatype, &column, shape, heap)?));
+            }
+            DynamicArray::Compound(columns)
+        }
+        _ => return Err(Error::UnsupportedDataType),
+    })
+}
+
+/// Whether the members of an enum are those that h5py and PyTables use for booleans.
+fn is_bool(members: &[(String, i64)]) -> bool {
+    members.len() == 2 && members.iter().all(|&(ref name, value)| (name == "FALSE" && value == 0) || (name == "TRUE" && value == 1))
+}
+
+fn fixed_string(data: &[u8], padding: StringPadding) -> String {
+    let end = match padding {
+        StringPadding::NullTerminated => data.iter().position(|&b| b == 0).unwrap_or(data.len()),
+        StringPadding::NullPadded => data.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1),
+        StringPadding::SpacePadded => data.iter().rposition(|&b| b != b' ' && b != 0).map_or(0, |i| i + 1),
+    };
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// Integer elements, sign extended to 64 bits.
+fn integers(datatype: &Datatype, data: &[u8]) -> Result<Vec<i64>> {
+    match *datatype {
+        Datatype::Integer { size, signed, order } => {
+            let shift = 64 - 8 * size;
+            Ok(data.chunks(size)
+                .map(|bytes| {
+                    let x = unsigned(bytes, order);
+                    if signed { (x << shift) as i64 >> shift } else { x as i64 }
+                })
+                .collect())
+        }
+        _ => Err(Error::UnsupportedDataType),
+    }
+}
+
+/// Convert elements of `size` bytes, which are passed to `f` as unsigned integer.
+fn convert<T, F: Fn(u64) -> T>(data: &[u8], shape: IxDyn, size: usize, order: ByteOrder, f: F) -> Result<Array<T, IxDyn>> {
+    let values = data.chunks(size)
+        .map(|bytes| f(unsigned(bytes, order)))
+        .collect();
+    Ok(Array::from_shape_vec(shape, values)?)
+}
+
+fn unsigned(bytes: &[u8], order: ByteOrder) -> u64 {
+    match order {
+        ByteOrder::LittleEndian => bytes.iter().rev().fold(0, |x, &b| x << 8 | b as u64),
+        ByteOrder::BigEndian => bytes.iter().fold(0, |x, &b| x << 8 | b as u64),
+    }
+}
@@ -0,0 +1,140 @@
+//! Links from groups to their members
+
+use super::io::{btree, Reader, UNDEFINED};
+use super::object::{ObjectHeader, LINK, LINK_INFO, SYMBOL_TABLE};
+use super::{Error, Result};
+
+/// Where a link points to.
+pub enum Link {
+    /// An object in the same file, given by the address of its object header
+    Hard(u64),
+
+    /// A path in the same file, absolute or relative to the group
+    Soft(String),
+
+    /// A path in another file
+    External {
+        file: String,
+        path: String,
+    },
+}
+
+/// All links of a group, sorted by name.
+pub fn links(reader: &Reader, header: &ObjectHeader) -> Result<Vec<(String, Link)>> {
+    let mut links = Vec::new();
+
+    // old style groups keep their links in a B-tree of symbol table nodes
+    if let Some(message) = header.find(SYMBOL_TABLE) {
+        let mut bytes = reader.bytes(&message.data);
+        let btree_address = bytes.address()?;
+        let heap = local_heap(reader, bytes.address()?)?;
+
+        let o = reader.offset_size();
+        let entry_size = 2 * o + 24;
+        btree(reader, btree_address, 0, reader.length_size(), &mut |_, address| {
+            let head = reader.read(address, 8)?;
+            let mut bytes = reader.bytes(&head);
+            bytes.signature(b"SNOD")?;
+            bytes.skip(2)?;
+            let n = bytes.u16()? as usize;
+
+            let data = reader.read(address + 8, n * entry_size)?;
+            let mut bytes = reader.bytes(&data);
+            for _ in 0..n {
+                let name = heap_string(&heap, bytes.uint(o)? as usize)?;
+                let address = bytes.address()?;
+                let cache_type = bytes.u32()?;
+                bytes.skip(4)?;
+                let scratch = bytes.take(16)?;
+
+                let link = if cache_type == 2 {
+                    let offset = reader.bytes(scratch).u32()? as usize;
+                    Link::Soft(heap_string(&heap, offset)?)
+                } else {
+                    Link::Hard(address)
+                };
+                links.push((name, link));
+            }
+            Ok(())
+        })?;
+    }
+
+    // new style groups keep a few links in the object header and many in a fractal heap
+    for message in header.find_all(LINK) {
+        links.push(parse_link(reader, &message.data)?);
+    }
+
+    if let Some(message) = header.find(LINK_INFO) {
+        let mut bytes = reader.bytes(&message.data);
+        bytes.skip(1)?;
+        if bytes.u8()? & 0x01 != 0 {
+            bytes.skip(8)?;
+        }
+        if bytes.address()? != UNDEFINED {
+            return Err(Error::Unsupported("dense link storage".to_owned()))
+        }
+    }
+
+    links.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(links)
+}
+
+/// Parse a link message.
+fn parse_link(reader: &Reader, data: &[u8]) -> Result<(String, Link)> {
+    let mut bytes = reader.bytes(data);
+    let version = bytes.u8()?;
+    if version != 1 {
+        return Err(Error::Unsupported(format!("link message version {}", version)))
+    }
+
+    let flags = bytes.u8()?;
+    let link_type = if flags & 0x08 != 0 { bytes.u8()? } else { 0 };
+    if flags & 0x04 != 0 {
+        bytes.skip(8)?;  // creation order
+    }
+    if flags & 0x10 != 0 {
+        bytes.skip(1)?;  // character set
+    }
+
+    let length = bytes.uint(1 << (flags & 0x03))? as usize;
+    let name = String::from_utf8_lossy(bytes.take(length)?).into_owned();
+
+    let link = match link_type {
+        0 => Link::Hard(bytes.address()?),
+        1 => {
+            let length = bytes.u16()? as usize;
+            Link::Soft(String::from_utf8_lossy(bytes.take(length)?).into_owned())
+        }
+        64 => {
+            let length = bytes.u16()? as usize;
+            let mut value = reader.bytes(bytes.take(length)?);
+            value.skip(1)?;
+            let file = value.cstring()?;
+            let path = value.cstring()?;
+            Link::External { file, path }
+        }
+        _ => return Err(Error::Unsupported(format!("link type {}", link_type))),
+    };
+
+    Ok((name, link))
+}
+
+/// Read the data segment of a local heap.
+fn local_heap(reader: &Reader, address: u64) -> Result<Vec<u8>> {
+    let head = reader.read(address, 8 + 2 * reader.length_size() + reader.offset_size())?;
+    let mut bytes = reader.bytes(&head);
+    bytes.signature(b"HEAP")?;
+    bytes.skip(4)?;
+    let size = bytes.length()? as usize;
+    bytes.length()?;
+    let data_address = bytes.address()?;
+    reader.read(data_address, size)
+}
+
+fn heap_string(heap: &[u8], offset: usize) -> Result<String> {
+    let data = heap.get(offset..).unwrap_or(&[]);
+    match data.iter().position(|&b| b == 0) {
+        Some(n) => Ok(String::from_utf8_lossy(&data[..n]).into_owned()),
+        None => Err(Error::Format("invalid name in local heap".to_owned())),
+    }
+}
@@ -0,0 +1,79 @@
+//! The global heap, which stores variable-length data
+
+use std::collections::HashMap;
+
+use super::io::Reader;
+use super::{Error, Result};
+
+/// Global heap collections, which are read on first access.
+pub struct GlobalHeap<'a> {
+    reader: &'a Reader,
+    collections: HashMap<u64, HashMap<u16, Vec<u8>>>,
+}
+
+impl<'a> GlobalHeap<'a> {
+    pub fn new(reader: &'a Reader) -> Self {
+        GlobalHeap {
+            reader,
+            collections: HashMap::new(),
+        }
+    }
+
+    /// The data of a variable-length element, which consists of the number of base elements of
+    /// given size and the location of the data in the global heap.
+    pub fn element(&mut self, element: &[u8], base_size: usize) -> Result<&[u8]> {
+        let mut bytes = self.reader.bytes(element);
+        let length = bytes.u32()? as usize * base_size;
+        let address = bytes.address()?;
+        let index = bytes.u32()?;
+        if length == 0 {
+            return Ok(&[])
+        }
+
+        self.get(address, index)?
+            .get(..length)
+            .ok_or_else(|| Error::Format("variable-length element exceeds its heap object".to_owned()))
+    }
+
+    /// The object with given index in the collection at given address.
+    pub fn get(&mut self, address: u64, index: u32) -> Result<&[u8]> {
+        if !self.collections.contains_key(&address) {
+            let collection = read_collection(self.reader, address)?;
+            self.collections.insert(address, collection);
+        }
+
+        self.collections[&address]
+            .get(&(index as u16))
+            .map(|object| &object[..])
+            .ok_or_else(|| Error::Format(format!("no object {} in global heap collection", index)))
+    }
+}
+
+fn read_collection(reader: &Reader, address: u64) -> Result<HashMap<u16, Vec<u8>>> {
+    let l = reader.length_size();
+    let head = reader.read(address, 8 + l)?;
+    let mut bytes = reader.bytes(&head);
+    bytes.signature(b"GCOL")?;
+    bytes.skip(4)?;
+    let size = bytes.length()? as usize;
+
+    let data = reader.read(address, size)?;
+    let mut bytes = reader.bytes(&data);
+    bytes.skip(8 + l)?;
+
+    let mut objects = HashMap::new();
+    while bytes.remaining() >= 8 + l {
+        let index = bytes.u16()?;
+        bytes.skip(6)?;
+        let size = bytes.length()? as usize;
+
+        // index 0 marks the free space at the end of the collection
+        if index == 0 {
+            break
+        }
+
+        objects.insert(index, bytes.take(size)?.to_owned());
+        bytes.skip(((size + 7) / 8 * 8 - size).min(bytes.remaining()))?;
+    }
+    Ok(objects)
+}
@@ -0,0 +1,299 @@
+//! Low level access to the bytes of an HDF5 file
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{Error, Result};
+
+/// Value of addresses that do not point anywhere, e.g. storage that has not been allocated.
+pub const UNDEFINED: u64 = !0;
+
+const SIGNATURE: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+/// An open HDF5 file with the parameters from its superblock.
+pub struct Reader {
+    file: Mutex<fs::File>,
+
+    /// Size of the file in bytes, which limits all reads
+    size: u64,
+    base: u64,
+    offset_size: usize,
+    length_size: usize,
+    root: u64,
+}
+
+impl Reader {
+    /// Open a file and read its superblock.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = fs::File::open(path).map_err(Error::IoError)?;
+        let size = file.metadata().map_err(Error::IoError)?.len();
+
+        // the superblock may be preceded by a user block of 512, 1024, 2048, ... bytes
+        let mut position = 0;
+        while position + SIGNATURE.len() as u64 <= size {
+            let mut signature = [0; 8];
+            file.seek(SeekFrom::Start(position)).map_err(Error::IoError)?;
+            file.read_exact(&mut signature).map_err(Error::IoError)?;
+            if signature == SIGNATURE {
+                return Reader::with_superblock(file, size, position)
+            }
+            position = if position == 0 { 512 } else { position * 2 };
+        }
+
+        Err(Error::Format("not an HDF5 file".to_owned()))
+    }
+
+    fn with_superblock(file: fs::File, size: u64, position: u64) -> Result<Self> {
+        let mut reader = Reader {
+            file: Mutex::new(file),
+            size,
+            base: 0,
+            offset_size: 8,
+            length_size: 8,
+            root: UNDEFINED,
+        };
+
+        // the base address is not known yet, so read relative to the start of the file
+        let head = reader.read(position, 16)?;
+        let version = head[8];
+        match version {
+            0 | 1 => {
+                reader.offset_size = head[13] as usize;
+                reader.length_size = head[14] as usize;
+                reader.check_sizes()?;
+
+                // versions, sizes, group K values, flags and (version 1 only) indexed storage K
+                let fixed = if version == 0 { 24 } else { 28 };
+                let data = reader.read(position, fixed + 6 * reader.offset_size + 24)?;
+                let mut bytes = reader.bytes(&data);
+                bytes.skip(fixed)?;
+                reader.base = bytes.address()?;
+                bytes.skip(3 * reader.offset_size)?;
+
+                // root group symbol table entry: link name offset, then object header address
+                bytes.skip(reader.offset_size)?;
+                reader.root = bytes.address()?;
+            }
+            2 | 3 => {
+                reader.offset_size = head[9] as usize;
+                reader.length_size = head[10] as usize;
+                reader.check_sizes()?;
+
+                let data = reader.read(position, 12 + 4 * reader.offset_size)?;
+                let mut bytes = reader.bytes(&data);
+                bytes.skip(12)?;
+                reader.base = bytes.address()?;
+                bytes.skip(2 * reader.offset_size)?;
+                reader.root = bytes.address()?;
+            }
+            _ => return Err(Error::Unsupported(format!("superblock version {}", version))),
+        }
+
+        if reader.base == UNDEFINED || reader.root == UNDEFINED {
+            return Err(Error::Format("superblock without root group".to_owned()))
+        }
+
+        Ok(reader)
+    }
+
+    fn check_sizes(&self) -> Result<()> {
+        match (self.offset_size, self.length_size) {
+            (2, 2) | (2, 4) | (2, 8) | (4, 2) | (4, 4) | (4, 8) | (8, 2) | (8, 4) | (8, 8) => Ok(()),
+            (o, l) => Err(Error::Unsupported(format!("{} byte offsets and {} byte lengths", o, l))),
+        }
+    }
+
+    /// Object header address of the root group.
+    pub fn root(&self) -> u64 {
+        self.root
+    }
+
+    /// Read `len` bytes starting at `address`.
+    ///
+    /// Addresses and lengths come from the file itself, so they are checked against the size of
+    /// the file before anything is allocated.
+    pub fn read(&self, address: u64, len: usize) -> Result<Vec<u8>> {
+        if address == UNDEFINED {
+            return Err(Error::Format("access to undefined address".to_owned()))
+        }
+
+        // addresses are relative to the base address, which is usually the superblock itself
+        let start = self.base.checked_add(address);
+        match start.and_then(|start| start.checked_add(len as u64)) {
+            Some(end) if end <= self.size => {}
+            _ => return Err(Error::Format(format!("{} bytes at address {} exceed the file", len, address))),
+        }
+
+        let mut data = vec![0; len];
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(self.base + address)).map_err(Error::IoError)?;
+        file.read_exact(&mut data).map_err(Error::IoError)?;
+        Ok(data)
+    }
+
+    /// Parse `data` with the address and length sizes of this file.
+    pub fn bytes<'a>(&self, data: &'a [u8]) -> Bytes<'a> {
+        Bytes {
+            data,
+            position: 0,
+            offset_size: self.offset_size,
+            length_size: self.length_size,
+        }
+    }
+
+    /// Size of addresses in bytes.
+    pub fn offset_size(&self) -> usize {
+        self.offset_size
+    }
+
+    /// Size of lengths in bytes.
+    pub fn length_size(&self) -> usize {
+        self.length_size
+    }
+}
+
+/// Cursor over little endian encoded data.
+pub struct Bytes<'a> {
+    data: &'a [u8],
+    position: usize,
+    offset_size: usize,
+    length_size: usize,
+}
+
+impl<'a> Bytes<'a> {
+    /// The next `n` bytes.
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.remaining() {
+            return Err(Error::Format("unexpected end of data".to_owned()))
+        }
+        let data = &self.data[self.position..self.position + n];
+        self.position += n;
+        Ok(data)
+    }
+
+    pub fn skip(&mut self, n: usize) -> Result<()> {
+        self.take(n).map(|_| ())
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        self.uint(2).map(|x| x as u16)
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        self.uint(4).map(|x| x as u32)
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        self.uint(8)
+    }
+
+    /// Unsigned integer of `n` bytes.
+    pub fn uint(&mut self, n: usize) -> Result<u64> {
+        Ok(self.take(n)?.iter().rev().fold(0, |x, &b| x << 8 | b as u64))
+    }
+
+    /// An address; [`UNDEFINED`](constant.UNDEFINED.html) if all bits are set.
+    pub fn address(&mut self) -> Result<u64> {
+        let n = self.offset_size;
+        let address = self.uint(n)?;
+        if n < 8 && address == (1 << (8 * n)) - 1 {
+            Ok(UNDEFINED)
+        } else {
+            Ok(address)
+        }
+    }
+
+    pub fn length(&mut self) -> Result<u64> {
+        let n = self.length_size;
+        self.uint(n)
+    }
+
+    /// Check for a four byte signature such as `b"TREE"`.
+    pub fn signature(&mut self, signature: &[u8]) -> Result<()> {
+        let found = self.take(signature.len())?;
+        if found == signature {
+            Ok(())
+        } else {
+            Err(Error::Format(format!("expected signature {}, found {:?}",
+                                      String::from_utf8_lossy(signature), found)))
+        }
+    }
+
+    /// A null terminated string.
+    pub fn cstring(&mut self) -> Result<String> {
+        let rest = &self.data[self.position..];
+        match rest.iter().position(|&b| b == 0) {
+            Some(n) => {
+                self.position += n + 1;
+                Ok(String::from_utf8_lossy(&rest[..n]).into_owned())
+            }
+            None => Err(Error::Format("unterminated string".to_owned())),
+        }
+    }
+
+    /// A null-terminated string that is padded with null bytes to a multiple of `align` bytes.
+    pub fn padded_cstring(&mut self, align: usize) -> Result<String> {
+        let start = self.position;
+        let s = self.cstring()?;
+        let length = self.position - start;
+        self.skip((length + align - 1) / align * align - length)?;
+        Ok(s)
+    }
+}
+
+/// Visit the leaves of a version 1 B-tree with the key to their left.
+pub fn btree(reader: &Reader, address: u64, node_type: u8, key_size: usize,
+             visit: &mut dyn FnMut(&[u8], u64) -> Result<()>) -> Result<()> {
+    btree_node(reader, address, node_type, key_size, None, &mut HashSet::new(), visit)
+}
+
+/// Visit the leaves below a B-tree node, which must be at the level `expected` unless it is the
+/// root.
+///
+/// Levels decrease towards the leaves and each node is visited once, so that corrupted files with
+/// cycles in the tree do not make this recurse forever.
+fn btree_node(reader: &Reader, address: u64, node_type: u8, key_size: usize, expected: Option<u8>,
+              visited: &mut HashSet<u64>, visit: &mut dyn FnMut(&[u8], u64) -> Result<()>) -> Result<()> {
+    if !visited.insert(address) {
+        return Err(Error::Format("B-tree node is referenced twice".to_owned()))
+    }
+
+    let o = reader.offset_size();
+    let head = reader.read(address, 8 + 2 * o)?;
+    let mut bytes = reader.bytes(&head);
+    bytes.signature(b"TREE")?;
+    if bytes.u8()? != node_type {
+        return Err(Error::Format("unexpected B-tree node type".to_owned()))
+    }
+    let level = bytes.u8()?;
+    if expected.is_some() && expected != Some(level) {
+        return Err(Error::Format("unexpected B-tree node level".to_owned()))
+    }
+    let n = bytes.u16()? as usize;
+
+    let body = address.checked_add(head.len() as u64)
+        .ok_or_else(|| Error::Format("B-tree node address out of range".to_owned()))?;
+    let body = reader.read(body, (n + 1) * key_size + n * o)?;
+    let mut bytes = reader.bytes(&body);
+    for _ in 0..n {
+        let key = bytes.take(key_size)?;
+        let child = bytes.address()?;
+        if level > 0 {
+            btree_node(reader, child, node_type, key_size, Some(level - 1), visited, visit)?;
+        } else {
+            visit(key, child)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1156 @@
+//! Read HDF5 files
+//!
+//! This is a reader for the subset of HDF5 that the mldata.org data sets use, written in pure
+//! Rust: groups, contiguous, compact and chunked datasets (optionally compressed with deflate),
+//! and numeric, string, enum and compound elements. Attributes of the file, groups and datasets can be
+//! read as numbers or strings.
+//!
+//! Datasets are opened by path, or discovered by traversing the groups of a file. Opening a
+//! dataset reads its shape and type; the data is only read by `Dataset::read` and friends. Parts
+//! of a dataset, such as a range of rows, are read with `Dataset::read_selection`, which only
+//! reads the data that contains the selected elements.
+
+use std;
+use std::error;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::result;
+use std::sync::{Arc, Mutex};
+use ndarray::{Array, IxDyn, ShapeError};
+
+mod attribute;
+mod datatype;
+mod group;
+mod heap;
+mod io;
+mod object;
+mod selection;
+mod storage;
+
+#[cfg(test)]
+pub mod writer;
+
+pub use self::attribute::Attribute;
+pub use self::datatype::{ByteOrder, Datatype, Field, Native, StringPadding};
+pub use self::selection::Selection;
+
+use self::group::Link;
+use self::heap::GlobalHeap;
+use self::io::{Bytes, Reader};
+use self::object::{ObjectHeader, DATASPACE, DATATYPE, FILTER_PIPELINE, LAYOUT, LINK, LINK_INFO, SYMBOL_TABLE};
+use self::storage::{ChunkIndex, Filter, Layout};
+
+/// Maximum number of soft links that are followed to resolve a path
+const MAX_LINK_DEPTH: usize = 16;
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    NdError(ShapeError),
+    UnsupportedDataType,
+
+    /// Not returned by this reader; kept for code that matches on the errors of the former
+    /// libhdf5 bindings
+    UnknownError,
+
+    /// The file is not a valid HDF5 file
+    Format(String),
+
+    /// The file uses a feature of HDF5 that is not supported
+    Unsupported(String),
+
+    /// There is no object at given path
+    NotFound(String),
+
+    /// Error while reading the dataset at given path
+    Dataset {
+        path: String,
+        source: Box<Error>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IoError(ref e) => write!(f, "{}", e),
+            Error::NdError(ref e) => write!(f, "{}", e),
+            Error::UnsupportedDataType => write!(f, "unsupported data type"),
+            Error::UnknownError => write!(f, "unknown error"),
+            Error::Format(ref msg) => write!(f, "invalid HDF5 file: {}", msg),
+            Error::Unsupported(ref feature) => write!(f, "unsupported HDF5 feature: {}", feature),
+            Error::NotFound(ref path) => write!(f, "no object at {}", path),
+            Error::Dataset { ref path, .. } => write!(f, "cannot read dataset {}", path),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            // transparent: their messages are already part of ours
+            Error::IoError(ref e) => e.source(),
+            Error::NdError(ref e) => e.source(),
+            Error::Dataset { ref source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
+
+impl From<ShapeError> for Error {
+    fn from(err: ShapeError) -> Self {
+        Error::NdError(err)
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum DynamicArray {
+    Int8(Array<i8, IxDyn>),
+    Int16(Array<i16, IxDyn>),
+    Int32(Array<i32, IxDyn>),
+    Int64(Array<i64, IxDyn>),
+    UInt8(Array<u8, IxDyn>),
+    UInt16(Array<u16, IxDyn>),
+    UInt32(Array<u32, IxDyn>),
+    UInt64(Array<u64, IxDyn>),
+    Float32(Array<f32, IxDyn>),
+    Float64(Array<f64, IxDyn>),
+
+    /// Fixed or variable-length strings
+    String(Array<String, IxDyn>),
+
+    /// Enums with the members `FALSE = 0` and `TRUE = 1`, which h5py uses for booleans
+    Bool(Array<bool, IxDyn>),
+
+    /// Values of any other enum, and the names of its members
+    Enum {
+        values: Array<i64, IxDyn>,
+        members: Vec<(String, i64)>,
+    },
+
+    /// An array for each field of a compound type, in the order in which they are declared
+    Compound(Vec<(String, DynamicArray)>),
+}
+
+pub struct File {
+    reader: Arc<Reader>,
+}
+
+impl File {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(File {
+            reader: Arc::new(Reader::open(path)?),
+        })
+    }
+
+    pub fn dataset(&self, name: &str) -> Result<Dataset> {
+        Dataset::new(self, name)
+    }
+
+    /// The root group.
+    pub fn root(&self) -> Result<Group> {
+        self.group("/")
+    }
+
+    /// The group at given path.
+    pub fn group(&self, path: &str) -> Result<Group> {
+        let address = resolve(&self.reader, self.reader.root(), path)?;
+        let header = ObjectHeader::read(&self.reader, address)?;
+        Group::from_header(&self.reader, address, path, &header)
+    }
+
+    /// All datasets in the file; see [`Group::walk`](struct.Group.html#method.walk).
+    pub fn datasets(&self) -> Result<Vec<Dataset>> {
+        self.root()?.walk()
+    }
+
+    /// Attributes of the root group, which HDF5 tools present as attributes of the file.
+    pub fn attributes(&self) -> Result<Vec<Attribute>> {
+        attribute::attributes(&self.reader, &ObjectHeader::read(&self.reader, self.reader.root())?)
+    }
+
+    /// The attribute of the root group with given name.
+    pub fn attribute(&self, name: &str) -> Result<Attribute> {
+        find_attribute(self.attributes()?, name)
+    }
+}
+
+/// The type of a link from a group to one of its members.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkType {
+    /// Link to an object in the same file
+    Hard,
+
+    /// Link to a path in the same file, which does not need to exist
+    Soft(String),
+
+    /// Link to a path in another file
+    External {
+        file: String,
+        path: String,
+    },
+}
+
+/// A group, which contains datasets and other groups.
+pub struct Group {
+    reader: Arc<Reader>,
+    path: String,
+    address: u64,
+    links: Vec<(String, Link)>,
+}
+
+impl Group {
+    fn from_header(reader: &Arc<Reader>, address: u64, path: &str, header: &ObjectHeader) -> Result<Self> {
+        if !is_group(header) {
+            return Err(Error::Format(format!("{} is not a group", path)))
+        }
+
+        Ok(Group {
+            reader: reader.clone(),
+            path: path.to_owned(),
+            address,
+            links: group::links(reader, header)?,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Names of all members, in alphabetical order.
+    pub fn names(&self) -> Vec<&str> {
+        self.links.iter().map(|link| link.0.as_str()).collect()
+    }
+
+    /// Names of all members and the types of the links to them, in alphabetical order.
+    pub fn links(&self) -> Vec<(&str, LinkType)> {
+        self.links.iter()
+            .map(|&(ref name, ref link)| {
+                let link_type = match *link {
+                    Link::Hard(_) => LinkType::Hard,
+                    Link::Soft(ref path) => LinkType::Soft(path.clone()),
+                    Link::External { ref file, ref path } => LinkType::External {
+                        file: file.clone(),
+                        path: path.clone(),
+                    },
+                };
+                (name.as_str(), link_type)
+            })
+            .collect()
+    }
+
+    /// The group at given path, relative to this group unless it is absolute.
+    pub fn group(&self, path: &str) -> Result<Group> {
+        let address = resolve(&self.reader, self.address, path)?;
+        let header = ObjectHeader::read(&self.reader, address)?;
+        Group::from_header(&self.reader, address, &self.join(path), &header)
+    }
+
+    /// The dataset at given path, relative to this group unless it is absolute.
+    pub fn dataset(&self, path: &str) -> Result<Dataset> {
+        let address = resolve(&self.reader, self.address, path)?;
+        Dataset::open(&self.reader, address, &self.join(path))
+    }
+
+    /// The groups in this group.
+    ///
+    /// Soft links are followed; links that cannot be resolved and external links are skipped.
+    pub fn groups(&self) -> Result<Vec<Group>> {
+        let mut groups = Vec::new();
+        for (path, address, header) in self.members()? {
+            if is_group(&header) {
+                groups.push(Group::from_header(&self.reader, address, &path, &header)?);
+            }
+        }
+        Ok(groups)
+    }
+
+    /// The datasets in this group, with their shape and type but without their data.
+    ///
+    /// Soft links are followed; links that cannot be resolved and external links are skipped.
+    pub fn datasets(&self) -> Result<Vec<Dataset>> {
+        let mut datasets = Vec::new();
+        for (path, address, header) in self.members()? {
+            if is_dataset(&header) {
+                datasets.push(Dataset::from_header(&self.reader, address, &path, &header)?);
+            }
+        }
+        Ok(datasets)
+    }
+
+    /// All datasets in this group and its subgroups, depth first and in alphabetical order within
+    /// each group.
+    ///
+    /// Groups that are reachable through several links are visited only once.
+    pub fn walk(&self) -> Result<Vec<Dataset>> {
+        let mut datasets = Vec::new();
+        let mut visited = HashSet::new();
+        visited.insert(self.address);
+        self.walk_into(&mut datasets, &mut visited)?;
+        Ok(datasets)
+    }
+
+    fn walk_into(&self, datasets: &mut Vec<Dataset>, visited: &mut HashSet<u64>) -> Result<()> {
+        for (path, address, header) in self.members()? {
+            if is_dataset(&header) {
+                datasets.push(Dataset::from_header(&self.reader, address, &path, &header)?);
+            } else if is_group(&header) && visited.insert(address) {
+                Group::from_header(&self.reader, address, &path, &header)?.walk_into(datasets, visited)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attributes of this group, in the order in which they are stored.
+    pub fn attributes(&self) -> Result<Vec<Attribute>> {
+        attribute::attributes(&self.reader, &ObjectHeader::read(&self.reader, self.address)?)
+    }
+
+    /// The attribute with given name.
+    pub fn attribute(&self, name: &str) -> Result<Attribute> {
+        find_attribute(self.attributes()?, name)
+    }
+
+    /// Path, object header address and object header of all members that can be resolved.
+    fn members(&self) -> Result<Vec<(String, u64, ObjectHeader)>> {
+        let mut members = Vec::new();
+        for &(ref name, ref link) in &self.links {
+            let address = match *link {
+                Link::Hard(address) => address,
+                Link::Soft(ref path) => match resolve(&self.reader, self.address, path) {
+                    Ok(address) => address,
+                    Err(Error::NotFound(_)) => continue,
+                    Err(e) => return Err(e),
+                },
+                Link::External { .. } => continue,
+            };
+            members.push((self.join(name), address, ObjectHeader::read(&self.reader, address)?));
+        }
+        Ok(members)
+    }
+
+    /// Path of a member, or of an object at a relative path.
+    fn join(&self, path: &str) -> String {
+        if path.starts_with('/') {
+            path.to_owned()
+        } else if self.path.ends_with('/') {
+            format!("{}{}", self.path, path)
+        } else {
+            format!("{}/{}", self.path, path)
+        }
+    }
+}
+
+fn find_attribute(attributes: Vec<Attribute>, name: &str) -> Result<Attribute> {
+    attributes.into_iter()
+        .find(|a| a.name() == name)
+        .ok_or_else(|| Error::NotFound(name.to_owned()))
+}
+
+fn is_group(header: &ObjectHeader) -> bool {
+    header.find(SYMBOL_TABLE).is_some() || header.find(LINK_INFO).is_some() || header.find(LINK).is_some()
+}
+
+fn is_dataset(header: &ObjectHeader) -> bool {
+    header.find(DATATYPE).is_some() && header.find(LAYOUT).is_some()
+}
+
+/// Object header address of the object at given path, which is relative to the group at given
+/// address unless it is absolute.
+fn resolve(reader: &Reader, group: u64, path: &str) -> Result<u64> {
+    resolve_links(reader, group, path, 0)
+        .map_err(|e| match e {
+            Error::NotFound(_) => Error::NotFound(path.to_owned()),
+            e => e,
+        })
+}
+
+fn resolve_links(reader: &Reader, group: u64, path: &str, depth: usize) -> Result<u64> {
+    let mut address = if path.starts_with('/') { reader.root() } else { group };
+
+    for name in path.split('/').filter(|name| !name.is_empty() && *name != ".") {
+        let header = ObjectHeader::read(reader, address)?;
+        let link = group::links(reader, &header)?
+            .into_iter()
+            .find(|link| link.0 == name)
+            .map(|link| link.1);
+
+        address = match link {
+            Some(Link::Hard(target)) => target,
+            Some(Link::Soft(ref target)) if depth < MAX_LINK_DEPTH => resolve_links(reader, address, target, depth + 1)?,
+            Some(Link::Soft(_)) => return Err(Error::Format("too many levels of soft links".to_owned())),
+            Some(Link::External { ref file, ref path }) => {
+                return Err(Error::Unsupported(format!("external link to {} in {}", path, file)))
+            }
+            None => return Err(Error::NotFound(path.to_owned())),
+        };
+    }
+
+    Ok(address)
+}
+
+pub struct Dataset {
+    reader: Arc<Reader>,
+    path: String,
+    address: u64,
+    datatype: Datatype,
+    space: Dataspace,
+    layout: Layout,
+    filters: Vec<Filter>,
+
+    /// Index of the chunks, which is read by the first read of a chunked dataset
+    chunks: Mutex<Option<Arc<ChunkIndex>>>,
+}
+
+impl Dataset {
+    pub fn new(file: &File, name: &str) -> Result<Self> {
+        let address = resolve(&file.reader, file.reader.root(), name)?;
+        Dataset::open(&file.reader, address, name)
+    }
+
+    fn open(reader: &Arc<Reader>, address: u64, path: &str) -> Result<Self> {
+        ObjectHeader::read(reader, address)
+            .and_then(|header| Dataset::from_header(reader, address, path, &header))
+            .map_err(|e| match e {
+                Error::Dataset { .. } => e,
+                e => Error::Dataset {
+                    path: path.to_owned(),
+                    source: Box::new(e),
+                },
+            })
+    }
+
+    fn from_header(reader: &Arc<Reader>, address: u64, path: &str, header: &ObjectHeader) -> Result<Self> {
+        Dataset::parse_header(reader, address, path, header)
+            .map_err(|e| Error::Dataset {
+                path: path.to_owned(),
+                source: Box::new(e),
+            })
+    }
+
+    fn parse_header(reader: &Arc<Reader>, address: u64, path: &str, header: &ObjectHeader) -> Result<Self> {
+        let datatype = match header.find(DATATYPE) {
+            Some(message) if message.is_shared() => return Err(Error::Unsupported("shared datatypes".to_owned())),
+            Some(message) => datatype::parse(&mut reader.bytes(&message.data))?,
+            None => return Err(Error::Format("object is not a dataset".to_owned())),
+        };
+
+        let space = match header.find(DATASPACE) {
+            Some(message) => Dataspace::parse(&mut reader.bytes(&message.data))?,
+            None => return Err(Error::Format("dataset without dataspace".to_owned())),
+        };
+
+        let layout = match header.find(LAYOUT) {
+            Some(message) => storage::parse_layout(&mut reader.bytes(&message.data))?,
+            None => return Err(Error::Format("dataset without layout".to_owned())),
+        };
+
+        let filters = match header.find(FILTER_PIPELINE) {
+            Some(message) => storage::parse_filters(&mut reader.bytes(&message.data))?,
+            None => Vec::new(),
+        };
+
+        Ok(Dataset {
+            reader: reader.clone(),
+            path: path.to_owned(),
+            address,
+            datatype,
+            space,
+            layout,
+            filters,
+            chunks: Mutex::new(None),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_type(&self) -> Datatype {
+        Datatype::new(self)
+    }
+
+    pub fn get_space(&self) -> Dataspace {
+        Dataspace::new(self)
+    }
+
+    /// Attributes of this dataset, in the order in which they are stored.
+    pub fn attributes(&self) -> Result<Vec<Attribute>> {
+        ObjectHeader::read(&self.reader, self.address)
+            .and_then(|header| attribute::attributes(&self.reader, &header))
+            .map_err(|e| self.error(e))
+    }
+
+    /// The attribute with given name.
+    pub fn attribute(&self, name: &str) -> Result<Attribute> {
+        find_attribute(self.attributes()?, name)
+    }
+
+    /// The index of the chunks, which is read once and shared by all reads of this dataset.
+    fn chunk_index(&self) -> Result<Arc<ChunkIndex>> {
+        let mut chunks = self.chunks.lock().unwrap();
+        if chunks.is_none() {
+            *chunks = Some(Arc::new(storage::chunk_index(&self.reader, &self.layout, &self.space.shape)?));
+        }
+        Ok(chunks.as_ref().unwrap().clone())
+    }
+
+    fn raw_read(&self) -> Result<Vec<u8>> {
+        storage::read(&self.reader, &self.layout, &self.filters, &*self.chunk_index()?, &self.space.shape,
+                      self.datatype.size())
+    }
+
+    pub fn read(&self) -> Result<DynamicArray> {
+        self.read_any().map_err(|e| self.error(e))
+    }
+
+    pub fn read_i32(&self) -> Result<Array<i32, IxDyn>> {
+        self.read_native_i32().map_err(|e| self.error(e))
+    }
+
+    pub fn read_f64(&self) -> Result<Array<f64, IxDyn>> {
+        self.read_native_f64().map_err(|e| self.error(e))
+    }
+
+    /// Read the selected elements, which have the shape of the selection.
+    pub fn read_selection(&self, selection: &Selection) -> Result<DynamicArray> {
+        self.read_selected(selection).map_err(|e| self.error(e))
+    }
+
+    /// Read a range of entries along the first dimension.
+    pub fn read_rows(&self, rows: Range<usize>) -> Result<DynamicArray> {
+        self.read_selection(&Selection::range(rows))
+    }
+
+    fn error(&self, err: Error) -> Error {
+        Error::Dataset {
+            path: self.path.clone(),
+            source: Box::new(err),
+        }
+    }
+
+    fn read_any(&self) -> Result<DynamicArray> {
+        if let Datatype::Other { .. } = self.datatype {
+            return Err(Error::UnsupportedDataType)
+        }
+        let data = self.raw_read()?;
+        let mut heap = GlobalHeap::new(&self.reader);
+        datatype::decode(&self.datatype, &data, &self.space.shape, &mut heap)
+    }
+
+    fn read_selected(&self, selection: &Selection) -> Result<DynamicArray> {
+        if let Datatype::Other { .. } = self.datatype {
+            return Err(Error::UnsupportedDataType)
+        }
+        let (shape, runs) = selection::runs(selection, &self.space.shape)?;
+        let data = storage::read_runs(&self.reader, &self.layout, &self.filters, &*self.chunk_index()?,
+                                      &self.space.shape, self.datatype.size(), &runs)?;
+        let mut heap = GlobalHeap::new(&self.reader);
+        datatype::decode(&self.datatype, &data, &shape, &mut heap)
+    }
+
+    fn read_native_i32(&self) -> Result<Array<i32, IxDyn>> {
+        if let Datatype::Integer { size: 4, signed: true, .. } = self.datatype {
+            if let DynamicArray::Int32(array) = self.read_any()? {
+                return Ok(array)
+            }
+        }
+        Err(Error::UnsupportedDataType)
+    }
+
+    fn read_native_f64(&self) -> Result<Array<f64, IxDyn>> {
+        if let Datatype::Float { size: 8, .. } = self.datatype {
+            if let DynamicArray::Float64(array) = self.read_any()? {
+                return Ok(array)
+            }
+        }
+        Err(Error::UnsupportedDataType)
+    }
+}
+
+/// The shape of a dataset or attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataspace {
+    shape: Vec<usize>,
+}
+
+impl Dataspace {
+    /// The shape of a dataset.
+    pub fn new(dset: &Dataset) -> Self {
+        dset.space.clone()
+    }
+
+    fn parse(bytes: &mut Bytes) -> Result<Self> {
+        let version = bytes.u8()?;
+        let ndims = bytes.u8()? as usize;
+        bytes.skip(1)?;  // flags
+
+        let null = match version {
+            1 => {
+                bytes.skip(5)?;
+                false
+            }
+            2 => bytes.u8()? == 2,
+            _ => return Err(Error::Unsupported(format!("dataspace message version {}", version))),
+        };
+
+        if null {
+            return Ok(Dataspace { shape: vec![0] })
+        }
+
+        let mut shape = Vec::with_capacity(ndims);
+        for _ in 0..ndims {
+            shape.push(bytes.length()? as usize);
+        }
+        Ok(Dataspace { shape })
+    }
+
+    /// Number of dimensions; 0 for scalars.
+    ///
+    /// This never fails; the `Result` is kept for compatibility with the former libhdf5 bindings.
+    pub fn ndims(&self) -> Result<usize> {
+        Ok(self.shape.len())
+    }
+
+    /// Size of each dimension.
+    ///
+    /// This never fails; the `Result` is kept for compatibility with the former libhdf5 bindings.
+    pub fn shape(&self) -> Result<Vec<usize>> {
+        Ok(self.shape.clone())
+    }
+
+    /// Number of elements.
+    pub fn size(&self) -> usize {
+        self.shape.iter().product()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use super::*;
+    use super::writer::*;
+
+    /// File with the layout of the mldata.org files: datasets in a group called `data`.
+    fn mldata_file(name: &str) -> TempFile {
+        let mut w = Writer::new();
+        let doubles = bytes(&[1.5f64, -2.0, 3.25, 0.0, 1e10, -7.5], 8, f64::to_bits);
+        let double0 = w.dataset(&[2, 3], float(8, false), &doubles, Storage::Contiguous);
+        let int1 = w.dataset(&[4], integer(4, true, false), &bytes(&[1i32, -2, 3, -4], 4, |x| x as u32 as u64), Storage::Contiguous);
+        let label = w.dataset(&[3], integer(1, false, false), &[7, 8, 9], Storage::Compact);
+        let big = w.dataset(&[2], integer(2, true, true), &[0x01, 0x02, 0xff, 0xfe], Storage::Contiguous);
+        let single = w.dataset(&[], float(4, false), &bytes(&[0.5f32], 4, |x| x.to_bits() as u64), Storage::Compact);
+        let data = w.group(&[("double0", Member::Hard(double0)), ("int1", Member::Hard(int1)),
+                             ("label", Member::Hard(label)), ("big", Member::Hard(big)),
+                             ("single", Member::Hard(single))]);
+        let root = w.group(&[("data", Member::Hard(data)), ("alias", Member::Soft("/data/int1")),
+                             ("dangling", Member::Soft("nothing/here"))]);
+        save(name, &w.finish(root))
+    }
+
+    #[test]
+    fn numeric() {
+        let path = mldata_file("numeric");
+        let file = File::open(&path).unwrap();
+
+        let double0 = file.dataset("/data/double0").unwrap();
+        assert_eq!(double0.get_space().shape().unwrap(), [2, 3]);
+        assert_eq!(double0.get_type(), Datatype::Float { size: 8, order: ByteOrder::LittleEndian });
+        assert_eq!(Datatype::new(&double0), Datatype::native::<f64>());
+        assert!(double0.get_type().is_native::<f64>() && !double0.get_type().is_native::<f32>());
+        assert_eq!(Dataspace::new(&double0).size(), 6);
+        assert_eq!(double0.read_f64().unwrap(), arr2(&[[1.5, -2.0, 3.25], [0.0, 1e10, -7.5]]).into_dyn());
+
+        assert_eq!(file.dataset("/data/int1").unwrap().read_i32().unwrap().into_raw_vec(), [1, -2, 3, -4]);
+        assert_eq!(file.dataset("data/int1").unwrap().read_i32().unwrap().into_raw_vec(), [1, -2, 3, -4]);
+
+        match file.dataset("/data/label").unwrap().read().unwrap() {
+            DynamicArray::UInt8(a) => assert_eq!(a.into_raw_vec(), [7, 8, 9]),
+            a => panic!("unexpected array {:?}", a),
+        }
+
+        match file.dataset("/data/big").unwrap().read().unwrap() {
+            DynamicArray::Int16(a) => assert_eq!(a.into_raw_vec(), [0x0102, -2]),
+            a => panic!("unexpected array {:?}", a),
+        }
+
+        let single = file.dataset("/data/single").unwrap();
+        assert_eq!(single.get_space().ndims().unwrap(), 0);
+        match single.read().unwrap() {
+            DynamicArray::Float32(a) => assert_eq!(a.into_raw_vec(), [0.5]),
+            a => panic!("unexpected array {:?}", a),
+        }
+    }
+
+    #[test]
+    fn chunked() {
+        let values: Vec<i32> = (0..35).collect();
+        let data = bytes(&values, 4, |x| x as u64);
+        let doubles: Vec<f64> = (0..35).map(|x| x as f64 / 4.0).collect();
+
+        let mut w = Writer::new();
+        let plain = w.dataset(&[5, 7], integer(4, true, false), &data, Storage::Chunked(&[2, 3], &[]));
+        let filtered = w.dataset(&[5, 7], float(8, false), &bytes(&doubles, 8, f64::to_bits),
+                                 Storage::Chunked(&[4, 4], &[(2, &[8]), (1, &[6]), (3, &[])]));
+        let vector = w.dataset(&[35], integer(4, true, false), &data, Storage::Chunked(&[10], &[(1, &[9])]));
+        let root = w.group(&[("plain", Member::Hard(plain)), ("filtered", Member::Hard(filtered)),
+                             ("vector", Member::Hard(vector))]);
+        let path = save("chunked", &w.finish(root));
+        let file = File::open(&path).unwrap();
+
+        let plain = file.dataset("plain").unwrap().read_i32().unwrap();
+        assert_eq!(plain.shape(), [5, 7]);
+        assert_eq!(plain.into_raw_vec(), values);
+
+        let filtered = file.dataset("filtered").unwrap().read_f64().unwrap();
+        assert_eq!(filtered.into_raw_vec(), doubles);
+
+        assert_eq!(file.dataset("vector").unwrap().read_i32().unwrap().into_raw_vec(), values);
+    }
+
+    #[test]
+    fn headers_and_links() {
+        let mut w = Writer::new();
+        let data = bytes(&[1u64, 2, 3], 8, |x| x);
+        let address = w.alloc(&data);
+        let mut layout = vec![3, 1];
+        layout.extend(bytes(&[address, 24], 8, |x| x));
+
+        // messages split into a continuation block, as happens when attributes are added later
+        let split = w.object_header(&[(0x0001, dataspace(&[3])), (0x0003, integer(8, false, false)),
+                                      (0x0008, layout)], Some(1));
+        let group = w.group(&[("split", Member::Hard(split))]);
+
+        // new style group in a file with version 2 superblock
+        let root = w.object_header_v2(&[(0x0006, link("old", group)), (0x0006, link("values", split))]);
+        let path = save("links", &w.finish_v2(root));
+        let file = File::open(&path).unwrap();
+
+        for path in &["/values", "/old/split", "old//./split"] {
+            match file.dataset(path).unwrap().read().unwrap() {
+                DynamicArray::UInt64(a) => assert_eq!(a.into_raw_vec(), [1, 2, 3]),
+                a => panic!("unexpected array {:?}", a),
+            }
+        }
+    }
+
+    #[test]
+    fn discovery() {
+        let path = mldata_file("discovery");
+        let file = File::open(&path).unwrap();
+
+        let root = file.root().unwrap();
+        assert_eq!(root.names(), ["alias", "dangling", "data"]);
+        assert_eq!(root.links()[0], ("alias", LinkType::Soft("/data/int1".to_owned())));
+        assert_eq!(root.links()[2], ("data", LinkType::Hard));
+
+        let groups = root.groups().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].path(), "/data");
+
+        let datasets = root.datasets().unwrap();
+        assert_eq!(datasets.len(), 1);
+        assert_eq!(datasets[0].path(), "/alias");
+        assert_eq!(datasets[0].get_space().shape().unwrap(), [4]);
+
+        let data = file.group("/data").unwrap();
+        let shapes: Vec<_> = data.datasets().unwrap().iter()
+            .map(|d| (d.path().to_owned(), d.get_space().shape().unwrap(), d.get_type().size()))
+            .collect();
+        assert_eq!(shapes, [("/data/big".to_owned(), vec![2], 2),
+                            ("/data/double0".to_owned(), vec![2, 3], 8),
+                            ("/data/int1".to_owned(), vec![4], 4),
+                            ("/data/label".to_owned(), vec![3], 1),
+                            ("/data/single".to_owned(), vec![], 4)]);
+        assert_eq!(data.dataset("int1").unwrap().read_i32().unwrap().into_raw_vec(), [1, -2, 3, -4]);
+        assert_eq!(data.dataset("/alias").unwrap().path(), "/alias");
+
+        let paths: Vec<_> = file.datasets().unwrap().iter().map(|d| d.path().to_owned()).collect();
+        assert_eq!(paths, ["/alias", "/data/big", "/data/double0", "/data/int1", "/data/label", "/data/single"]);
+
+        assert!(file.group("/data/int1").is_err());
+        assert!(root.group("data").unwrap().group("..").is_err());
+    }
+
+    #[test]
+    fn attributes() {
+        let mut w = Writer::new();
+        let heap = w.global_heap(&[b"sepal length", b"petal width"]);
+        let columns: Vec<u8> = (1..3).flat_map(|i| heap_reference([12, 11][i - 1], heap, i as u32)).collect();
+
+        w.attach(attribute("layout", &[2], integer(4, true, false), &bytes(&[1i32, 0], 4, |x| x as u64)));
+        let values = w.dataset(&[2], float(8, false), &bytes(&[1.0f64, 2.0], 8, f64::to_bits), Storage::Contiguous);
+        w.attach(attribute("columns", &[2], var_string(), &columns));
+        w.attach(attribute("unit", &[], fixed_string(8), b"cm\0\0\0\0\0\0"));
+        let data = w.group(&[("values", Member::Hard(values))]);
+        w.attach(attribute("description", &[], fixed_string(11), b"Iris plants"));
+        let root = w.group(&[("data", Member::Hard(data))]);
+        let path = save("attributes", &w.finish(root));
+        let file = File::open(&path).unwrap();
+
+        assert_eq!(file.attribute("description").unwrap().read_string().unwrap(), "Iris plants");
+        assert_eq!(file.attributes().unwrap().len(), 1);
+
+        let group = file.group("data").unwrap();
+        let names: Vec<_> = group.attributes().unwrap().iter().map(|a| a.name().to_owned()).collect();
+        assert_eq!(names, ["columns", "unit"]);
+        let columns = group.attribute("columns").unwrap();
+        assert_eq!(columns.get_type(), Datatype::VarString { size: 16 });
+        assert_eq!(columns.get_space().shape().unwrap(), [2]);
+        assert_eq!(columns.read_strings().unwrap(), ["sepal length", "petal width"]);
+        assert!(columns.read_string().is_err());
+        assert_eq!(group.attribute("unit").unwrap().read_string().unwrap(), "cm");
+
+        let layout = file.dataset("data/values").unwrap().attribute("layout").unwrap();
+        match layout.read().unwrap() {
+            DynamicArray::Int32(a) => assert_eq!(a.into_raw_vec(), [1, 0]),
+            a => panic!("unexpected array {:?}", a),
+        }
+        assert!(layout.read_strings().is_err());
+
+        match group.attribute("missing") {
+            Err(Error::NotFound(ref name)) => assert_eq!(name, "missing"),
+            _ => panic!("expected missing attribute"),
+        }
+    }
+
+    #[test]
+    fn strings_and_records() {
+        let mut w = Writer::new();
+        let heap = w.global_heap(&[b"setosa", b"virginica", b""]);
+        let labels: Vec<u8> = [(6, 1), (9, 2), (0, 3)].iter()
+            .flat_map(|&(length, index)| heap_reference(length, heap, index))
+            .collect();
+        let labels = w.dataset(&[3], var_string(), &labels, Storage::Contiguous);
+        let names = w.dataset(&[2], fixed_string(5), b"sepalpetal", Storage::Compact);
+
+        let boolean = enumeration(integer(1, true, false), &[("FALSE", 0), ("TRUE", 1)]);
+        let flags = w.dataset(&[3], boolean.clone(), &[1, 0, 1], Storage::Compact);
+        let colors = enumeration(integer(2, true, true), &[("red", 0), ("green", 1), ("blue", !0)]);
+        let species = w.dataset(&[2, 2], colors, &[0, 1, 0xff, 0xff, 0, 0, 0, 1], Storage::Contiguous);
+
+        let record = compound(1, 36, &[("id", 0, integer(4, true, false)), ("weight", 8, float(8, false)),
+                                       ("code", 16, fixed_string(4)), ("label", 20, var_string())]);
+        let mut records = Vec::new();
+        for &(id, weight, code, label) in &[(7u64, 2.5f64, b"ab\0\0", 1), (-1i64 as u64, -0.5, b"wxyz", 2)] {
+            records.extend(bytes(&[id], 4, |x| x));
+            records.extend(&[0; 4]);
+            records.extend(bytes(&[weight], 8, f64::to_bits));
+            records.extend(code);
+            records.extend(heap_reference(if label == 1 { 6 } else { 9 }, heap, label));
+        }
+        let records = w.dataset(&[2], record, &records, Storage::Contiguous);
+        let packed = compound(3, 3, &[("flag", 0, boolean), ("count", 1, integer(2, false, false))]);
+        let packed = w.dataset(&[1], packed, &[1, 0x34, 0x12], Storage::Compact);
+
+        let root = w.group(&[("labels", Member::Hard(labels)), ("names", Member::Hard(names)),
+                             ("flags", Member::Hard(flags)), ("species", Member::Hard(species)),
+                             ("records", Member::Hard(records)), ("packed", Member::Hard(packed))]);
+        let path = save("strings", &w.finish(root));
+        let file = File::open(&path).unwrap();
+
+        match file.dataset("labels").unwrap().read().unwrap() {
+            DynamicArray::String(a) => assert_eq!(a.into_raw_vec(), ["setosa", "virginica", ""]),
+            a => panic!("unexpected array {:?}", a),
+        }
+        match file.dataset("names").unwrap().read().unwrap() {
+            DynamicArray::String(a) => assert_eq!(a.into_raw_vec(), ["sepal", "petal"]),
+            a => panic!("unexpected array {:?}", a),
+        }
+        match file.dataset("flags").unwrap().read().unwrap() {
+            DynamicArray::Bool(a) => assert_eq!(a.into_raw_vec(), [true, false, true]),
+            a => panic!("unexpected array {:?}", a),
+        }
+
+        let species = file.dataset("species").unwrap();
+        match species.get_type() {
+            Datatype::Enum { ref base, .. } => assert_eq!(base.size(), 2),
+            ref t => panic!("unexpected type {:?}", t),
+        }
+        match species.read().unwrap() {
+            DynamicArray::Enum { values, members } => {
+                assert_eq!(values, arr2(&[[1, -1], [0, 1]]).into_dyn());
+                assert_eq!(members, [("red".to_owned(), 0), ("green".to_owned(), 1), ("blue".to_owned(), -1)]);
+            }
+            a => panic!("unexpected array {:?}", a),
+        }
+
+        match file.dataset("records").unwrap().read().unwrap() {
+            DynamicArray::Compound(fields) => {
+                let names: Vec<_> = fields.iter().map(|f| f.0.as_str()).collect();
+                assert_eq!(names, ["id", "weight", "code", "label"]);
+                match fields[0].1 {
+                    DynamicArray::Int32(ref a) => assert_eq!(a.as_slice().unwrap(), [7, -1]),
+                    ref a => panic!("unexpected array {:?}", a),
+                }
+                match fields[1].1 {
+                    DynamicArray::Float64(ref a) => assert_eq!(a.as_slice().unwrap(), [2.5, -0.5]),
+                    ref a => panic!("unexpected array {:?}", a),
+                }
+                match fields[2].1 {
+                    DynamicArray::String(ref a) => assert_eq!(a.as_slice().unwrap(), ["ab", "wxyz"]),
+                    ref a => panic!("unexpected array {:?}", a),
+                }
+                match fields[3].1 {
+                    DynamicArray::String(ref a) => assert_eq!(a.as_slice().unwrap(), ["setosa", "virginica"]),
+                    ref a => panic!("unexpected array {:?}", a),
+                }
+            }
+            a => panic!("unexpected array {:?}", a),
+        }
+
+        match file.dataset("packed").unwrap().read().unwrap() {
+            DynamicArray::Compound(fields) => match (&fields[0].1, &fields[1].1) {
+                (&DynamicArray::Bool(ref flag), &DynamicArray::UInt16(ref count)) => {
+                    assert_eq!(flag.as_slice().unwrap(), [true]);
+                    assert_eq!(count.as_slice().unwrap(), [0x1234]);
+                }
+                a => panic!("unexpected arrays {:?}", a),
+            },
+            a => panic!("unexpected array {:?}", a),
+        }
+    }
+
+    #[test]
+    fn selections() {
+        let values: Vec<i32> = (0..35).collect();
+        let data = bytes(&values, 4, |x| x as u64);
+
+        let mut w = Writer::new();
+        let contiguous = w.dataset(&[5, 7], integer(4, true, false), &data, Storage::Contiguous);
+        let compact = w.dataset(&[5, 7], integer(4, true, false), &data, Storage::Compact);
+        let chunked = w.dataset(&[5, 7], integer(4, true, false), &data, Storage::Chunked(&[2, 3], &[(1, &[6])]));
+        let root = w.group(&[("contiguous", Member::Hard(contiguous)), ("compact", Member::Hard(compact)),
+                             ("chunked", Member::Hard(chunked))]);
+        let path = save("selections", &w.finish(root));
+        let file = File::open(&path).unwrap();
+
+        let full = Array::from_shape_vec((5, 7), values).unwrap();
+        let read = |dataset: &Dataset, selection: Selection| match dataset.read_selection(&selection).unwrap() {
+            DynamicArray::Int32(a) => a,
+            a => panic!("unexpected array {:?}", a),
+        };
+
+        for name in &["contiguous", "compact", "chunked"] {
+            let dataset = file.dataset(name).unwrap();
+
+            match dataset.read_rows(1..3).unwrap() {
+                DynamicArray::Int32(a) => assert_eq!(a, full.slice(s![1..3, ..]).into_dyn()),
+                a => panic!("unexpected array {:?}", a),
+            }
+            assert_eq!(read(&dataset, Selection::strided(0..5, 2)), full.slice(s![0..5;2, ..]).into_dyn());
+            assert_eq!(read(&dataset, Selection::range(4..4)).shape(), [0, 7]);
+            assert_eq!(read(&dataset, Selection::strided(1..5, !0)), full.slice(s![1..2, ..]).into_dyn());
+
+            let slab = Selection::Hyperslab { start: vec![1, 2], step: vec![3, 2], count: vec![2, 3] };
+            assert_eq!(read(&dataset, slab), full.slice(s![1..5;3, 2..7;2]).into_dyn());
+            let columns = Selection::Hyperslab { start: vec![0, 5], step: vec![1, 1], count: vec![5, 2] };
+            assert_eq!(read(&dataset, columns), full.slice(s![.., 5..7]).into_dyn());
+
+            let rows = read(&dataset, Selection::Rows(vec![4, 0, 4]));
+            assert_eq!(rows.shape(), [3, 7]);
+            assert_eq!(rows.as_slice().unwrap()[..7], full.as_slice().unwrap()[28..]);
+            assert_eq!(rows.as_slice().unwrap()[7..14], full.as_slice().unwrap()[..7]);
+
+            let points = read(&dataset, Selection::Points(vec![vec![0, 0], vec![3, 6], vec![2, 3]]));
+            assert_eq!(points.into_raw_vec(), [0, 27, 17]);
+
+            // the chunk index is read once per dataset
+            let index = dataset.chunk_index().unwrap();
+            assert_eq!(index.len(), if *name == "chunked" { 9 } else { 0 });
+            assert!(Arc::ptr_eq(&index, &dataset.chunk_index().unwrap()));
+
+            let far = Selection::Hyperslab { start: vec![!0, 0], step: vec![1, 1], count: vec![2, 1] };
+            let huge = Selection::Hyperslab { start: vec![0, 0], step: vec![1 << 62, 1], count: vec![5, 1] };
+            for selection in &[Selection::range(4..6), Selection::Rows(vec![5]), Selection::Points(vec![vec![0, 7]]),
+                               Selection::Points(vec![vec![0]]), far, huge] {
+                match dataset.read_selection(selection) {
+                    Err(Error::Dataset { ref source, .. }) => match **source {
+                        Error::NdError(_) => {}
+                        ref e => panic!("unexpected error {}", e),
+                    },
+                    _ => panic!("expected error for {:?}", selection),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let path = mldata_file("errors");
+        let file = File::open(&path).unwrap();
+
+        assert_eq!(file.dataset("/alias").unwrap().read_i32().unwrap().into_raw_vec(), [1, -2, 3, -4]);
+
+        match file.dataset("/data/missing") {
+            Err(Error::NotFound(ref path)) => assert_eq!(path, "/data/missing"),
+            _ => panic!("expected missing dataset"),
+        }
+
+        match file.dataset("/data") {
+            Err(Error::Dataset { ref path, .. }) => assert_eq!(path, "/data"),
+            _ => panic!("expected error for group"),
+        }
+
+        match file.dataset("/data/double0").unwrap().read_i32() {
+            Err(Error::Dataset { ref source, .. }) => match **source {
+                Error::UnsupportedDataType => {}
+                ref e => panic!("unexpected error {}", e),
+            },
+            _ => panic!("expected data type error"),
+        }
+
+        let path = save("not-hdf5", b"just some text");
+        match File::open(&path) {
+            Err(Error::Format(_)) => {}
+            _ => panic!("expected format error"),
+        }
+    }
+
+    #[test]
+    fn corrupted() {
+        fn format_error<T>(result: Result<T>) -> bool {
+            match result {
+                Err(Error::Format(_)) => true,
+                Err(Error::Dataset { source, .. }) => format_error::<()>(Err(*source)),
+                _ => false,
+            }
+        }
+
+        let mut w = Writer::new();
+        let dataset = |w: &mut Writer, shape: &[usize], address: u64| {
+            let mut layout = vec![3, 1];
+            layout.extend(bytes(&[address, 8], 8, |x| x));
+            w.object_header(&[(0x0001, dataspace(shape)), (0x0003, float(8, false)), (0x0008, layout)], None)
+        };
+        let beyond = dataset(&mut w, &[4], 1 << 20);
+        let huge = dataset(&mut w, &[1 << 40], 0);
+        let overflow = dataset(&mut w, &[1 << 62, 16], 0);
+        let wrapping = dataset(&mut w, &[4], !0 - 8);
+        let looped = w.object_header_loop(&[(0x0001, dataspace(&[1]))]);
+        let tree = w.group_loop();
+        let root = w.group(&[("beyond", Member::Hard(beyond)), ("huge", Member::Hard(huge)),
+                             ("overflow", Member::Hard(overflow)), ("wrapping", Member::Hard(wrapping)),
+                             ("looped", Member::Hard(looped)), ("tree", Member::Hard(tree))]);
+        let path = save("corrupted", &w.finish(root));
+        let file = File::open(&path).unwrap();
+
+        for name in &["beyond", "huge", "overflow", "wrapping"] {
+            assert!(format_error(file.dataset(name).unwrap().read()), "{}", name);
+        }
+        for name in &["beyond", "wrapping"] {
+            assert!(format_error(file.dataset(name).unwrap().read_rows(1..2)), "{}", name);
+        }
+        assert!(format_error(file.dataset("looped")));
+        assert!(format_error(file.group("tree").and_then(|g| g.datasets())));
+        assert!(format_error(file.dataset("tree/anything")));
+
+        // a chunk with a checksum that does not match its data
+        let values: Vec<i32> = (1000..1012).collect();
+        let data = bytes(&values, 4, |x| x as u64);
+        let mut w = Writer::new();
+        let checked = w.dataset(&[12], integer(4, true, false), &data, Storage::Chunked(&[6], &[(3, &[])]));
+        let root = w.group(&[("checked", Member::Hard(checked))]);
+        let mut contents = w.finish(root);
+        let path = save("checksum", &contents);
+        let file = File::open(&path).unwrap();
+        assert_eq!(file.dataset("checked").unwrap().read_i32().unwrap().into_raw_vec(), values);
+
+        let position = contents.windows(data.len() / 2).position(|w| w == &data[..data.len() / 2]).unwrap();
+        contents[position] ^= 1;
+        let path = save("checksum", &contents);
+        let file = File::open(&path).unwrap();
+        let dataset = file.dataset("checked").unwrap();
+        assert!(format_error(dataset.read()));
+        assert!(format_error(dataset.read_rows(0..1)));
+        assert!(dataset.read_rows(6..12).is_ok());
+
+        // a compressed chunk that inflates to more than the chunk size declared by the layout
+        let mut w = Writer::new();
+        let deflated = w.dataset(&[12], integer(4, true, false), &data, Storage::Chunked(&[12], &[(1, &[6])]));
+        let root = w.group(&[("deflated", Member::Hard(deflated))]);
+        let mut contents = w.finish(root);
+        let dimensions = bytes(&[12, 4], 4, |x| x);
+        let positions: Vec<_> = contents.windows(8).enumerate().filter(|&(_, w)| w == &dimensions[..]).map(|(i, _)| i).collect();
+        assert_eq!(positions.len(), 1);
+        contents[positions[0]] = 6;
+        let path = save("inflated", &contents);
+        let file = File::open(&path).unwrap();
+        let dataset = file.dataset("deflated").unwrap();
+        assert!(format_error(dataset.read()));
+        assert!(format_error(dataset.read_rows(0..1)));
+    }
+
+    // Files written by the HDF5 library; see data/hdf5/generate.py.
+
+    #[test]
+    #[ignore]
+    fn library_mldata() {
+        let file = File::open("data/hdf5/mldata.hdf5").unwrap();
+        let data = file.dataset("/data/data").unwrap();
+        assert_eq!(data.get_space().shape().unwrap(), [4, 5]);
+        match data.read().unwrap() {
+            DynamicArray::UInt8(a) => assert_eq!(a.into_raw_vec(), (0..20).collect::<Vec<u8>>()),
+            a => panic!("unexpected array {:?}", a),
+        }
+        match data.read_selection(&Selection::Hyperslab { start: vec![0, 1], step: vec![1, 1], count: vec![4, 2] }).unwrap() {
+            DynamicArray::UInt8(a) => assert_eq!(a.into_raw_vec(), [1, 2, 6, 7, 11, 12, 16, 17]),
+            a => panic!("unexpected array {:?}", a),
+        }
+        assert_eq!(file.dataset("/data/label").unwrap().read_f64().unwrap().into_raw_vec(), [0.0, 1.0, 2.0, 1.0, 0.0]);
+        assert_eq!(file.dataset("/data/double0").unwrap().read_f64().unwrap(),
+                   arr2(&[[1.5, -2.0, 3.25], [0.0, 1e10, -7.5]]).into_dyn());
+        assert_eq!(file.dataset("/data/int1").unwrap().read_i32().unwrap().into_raw_vec(), [1, -2, 3, -4]);
+        match file.dataset("/data_descr/ordering").unwrap().read().unwrap() {
+            DynamicArray::String(a) => assert_eq!(a.into_raw_vec(), ["data", "label"]),
+            a => panic!("unexpected array {:?}", a),
+        }
+        assert_eq!(file.attribute("name").unwrap().read_string().unwrap(), "test");
+    }
+
+    #[test]
+    #[ignore]
+    fn library_chunked() {
+        let file = File::open("data/hdf5/chunked.hdf5").unwrap();
+        let values: Vec<f64> = (0..35 * 11).map(|x| x as f64 / 4.0).collect();
+        for name in &["plain", "deflate", "shuffle", "fletcher32"] {
+            let dataset = file.dataset(name).unwrap();
+            assert_eq!(dataset.read_f64().unwrap().into_raw_vec(), values, "{}", name);
+            match dataset.read_rows(9..17).unwrap() {
+                DynamicArray::Float64(a) => assert_eq!(a.into_raw_vec(), &values[9 * 11..17 * 11], "{}", name),
+                a => panic!("unexpected array {:?}", a),
+            }
+        }
+
+        let sparse = file.dataset("sparse").unwrap().read_f64().unwrap().into_raw_vec();
+        assert_eq!(sparse[0], 1.0);
+        assert!(sparse[1..].iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    #[ignore]
+    fn library_latest() {
+        let file = File::open("data/hdf5/latest.hdf5").unwrap();
+        for path in &["/group/values", "/link"] {
+            match file.dataset(path).unwrap().read().unwrap() {
+                DynamicArray::UInt64(a) => assert_eq!(a.into_raw_vec(), [1, 2, 3]),
+                a => panic!("unexpected array {:?}", a),
+            }
+        }
+        match file.dataset("/group/strings").unwrap().read().unwrap() {
+            DynamicArray::String(a) => assert_eq!(a.into_raw_vec(), ["a", "bc", "def"]),
+            a => panic!("unexpected array {:?}", a),
+        }
+
+        let group = file.group("group").unwrap();
+        assert_eq!(group.attribute("description").unwrap().read_string().unwrap(), "variable length string");
+        match group.attribute("scale").unwrap().read().unwrap() {
+            DynamicArray::Float32(a) => assert_eq!(a.into_raw_vec(), [0.5]),
+            a => panic!("unexpected array {:?}", a),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Object headers and the messages they contain
+
+use std::collections::HashSet;
+
+use super::io::{Reader, UNDEFINED};
+use super::{Error, Result};
+
+pub const DATASPACE: u16 = 0x0001;
+pub const LINK_INFO: u16 = 0x0002;
+pub const DATATYPE: u16 = 0x0003;
+pub const LINK: u16 = 0x0006;
+pub const LAYOUT: u16 = 0x0008;
+pub const FILTER_PIPELINE: u16 = 0x000B;
+pub const ATTRIBUTE: u16 = 0x000C;
+pub const CONTINUATION: u16 = 0x0010;
+pub const SYMBOL_TABLE: u16 = 0x0011;
+pub const ATTRIBUTE_INFO: u16 = 0x0015;
+
+/// The message is stored elsewhere and shared between objects.
+const SHARED: u8 = 0x02;
+
+/// A header message.
+pub struct Message {
+    pub kind: u16,
+    pub flags: u8,
+    pub data: Vec<u8>,
+}
+
+impl Message {
+    pub fn is_shared(&self) -> bool {
+        self.flags & SHARED != 0
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Format {
+    V1,
+    V2 { creation_order: bool },
+}
+
+/// The header of an object, i.e. of a group, dataset or named datatype.
+pub struct ObjectHeader {
+    pub messages: Vec<Message>,
+}
+
+impl ObjectHeader {
+    pub fn read(reader: &Reader, address: u64) -> Result<Self> {
+        let (format, first) = if reader.read(address, 4)? == b"OHDR" {
+            let prefix = reader.read(address + 4, 2)?;
+            let (version, flags) = (prefix[0], prefix[1]);
+            if version != 2 {
+                return Err(Error::Unsupported(format!("object header version {}", version)))
+            }
+
+            let mut offset = 6;
+            if flags & 0x20 != 0 {
+                offset += 16;  // access, modification, change and birth times
+            }
+            if flags & 0x10 != 0 {
+                offset += 4;  // attribute storage phase change values
+            }
+
+            let width = 1 << (flags & 0x03);
+            let size = reader.bytes(&reader.read(address + offset, width)?).uint(width)?;
+            (Format::V2 { creation_order: flags & 0x04 != 0 }, (address + offset + width as u64, size))
+        } else {
+            let prefix = reader.read(address, 16)?;
+            if prefix[0] != 1 {
+                return Err(Error::Unsupported(format!("object header version {}", prefix[0])))
+            }
+            let size = reader.bytes(&prefix[8..12]).u32()?;
+            (Format::V1, (address + 16, size as u64))
+        };
+
+        let mut header = ObjectHeader {
+            messages: Vec::new(),
+        };
+
+        let mut blocks = vec![first];
+        let mut visited = HashSet::new();
+        let mut i = 0;
+        while i < blocks.len() {
+            let (address, size) = blocks[i];
+            if !visited.insert(address) {
+                return Err(Error::Format("object header continuation blocks form a loop".to_owned()))
+            }
+            if size > usize::max_value() as u64 {
+                return Err(Error::Format("object header block too large".to_owned()))
+            }
+            let data = reader.read(address, size as usize)?;
+
+            // version 2 continuation blocks have a signature and a checksum
+            let data = match format {
+                Format::V2 { .. } if i > 0 => {
+                    reader.bytes(&data).signature(b"OCHK")?;
+                    if data.len() < 8 {
+                        return Err(Error::Format("object header continuation block too small".to_owned()))
+                    }
+                    &data[4..data.len() - 4]
+                }
+                _ => &data[..],
+            };
+
+            header.read_messages(reader, format, data, &mut blocks)?;
+            i += 1;
+        }
+
+        Ok(header)
+    }
+
+    fn read_messages(&mut self, reader: &Reader, format: Format, data: &[u8], blocks: &mut Vec<(u64, u64)>) -> Result<()> {
+        let mut bytes = reader.bytes(data);
+        loop {
+            let (kind, size, flags) = match format {
+                Format::V1 => {
+                    if bytes.remaining() < 8 {
+                        break
+                    }
+                    let kind = bytes.u16()?;
+                    let size = bytes.u16()?;
+                    let flags = bytes.u8()?;
+                    bytes.skip(3)?;
+                    (kind, size, flags)
+                }
+                Format::V2 { creation_order } => {
+                    // remaining space too small for a message is a gap
+                    if bytes.remaining() < if creation_order { 6 } else { 4 } {
+                        break
+                    }
+                    let kind = bytes.u8()? as u16;
+                    let size = bytes.u16()?;
+                    let flags = bytes.u8()?;
+                    if creation_order {
+                        bytes.skip(2)?;
+                    }
+                    (kind, size, flags)
+                }
+            };
+
+            let data = bytes.take(size as usize)?;
+            match kind {
+                0 => {}
+                CONTINUATION => {
+                    let mut data = reader.bytes(data);
+                    let address = data.address()?;
+                    let size = data.length()?;
+                    if address != UNDEFINED {
+                        blocks.push((address, size));
+                    }
+                }
+                _ => self.messages.push(Message {
+                    kind,
+                    flags,
+                    data: data.to_owned(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// The first message of given type.
+    pub fn find(&self, kind: u16) -> Option<&Message> {
+        self.messages.iter().find(|m| m.kind == kind)
+    }
+
+    /// All messages of given type.
+    pub fn find_all<'a>(&'a self, kind: u16) -> impl Iterator<Item=&'a Message> + 'a {
+        self.messages.iter().filter(move |m| m.kind == kind)
+    }
+}
@@ -0,0 +1,172 @@
+//! Selections of elements of a dataset
+
+use std::ops::Range;
+
+use ndarray::{ErrorKind, ShapeError};
+
+use super::{Error, Result};
+
+/// Consecutive elements, given by the index of the first element and the number of elements.
+pub type Run = (usize, usize);
+
+/// Elements of a dataset to read, without reading the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    /// `count[d]` elements that are `step[d]` apart, starting at `start[d]`, in each dimension
+    ///
+    /// Dimensions that are left out at the end are selected completely.
+    Hyperslab {
+        start: Vec<usize>,
+        step: Vec<usize>,
+        count: Vec<usize>,
+    },
+
+    /// Entries along the first dimension at given indices, in that order
+    Rows(Vec<usize>),
+
+    /// Single elements at given indices, in that order
+    Points(Vec<Vec<usize>>),
+}
+
+impl Selection {
+    /// Entries in a range along the first dimension.
+    pub fn range(range: Range<usize>) -> Self {
+        Selection::strided(range, 1)
+    }
+
+    /// Every `step`th entry in a range along the first dimension.
+    pub fn strided(range: Range<usize>, step: usize) -> Self {
+        let step = step.max(1);
+        let length = range.end.saturating_sub(range.start);
+        Selection::Hyperslab {
+            start: vec![range.start],
+            step: vec![step],
+            count: vec![if length == 0 { 0 } else { (length - 1) / step + 1 }],
+        }
+    }
+}
+
+/// Shape of the selected elements of a dataset of given shape, and the runs that make them up in
+/// row-major order.
+pub fn runs(selection: &Selection, shape: &[usize]) -> Result<(Vec<usize>, Vec<Run>)> {
+    let rank = shape.len();
+    let strides = strides(shape)?;
+    let mut runs = Vec::new();
+
+    match *selection {
+        Selection::Hyperslab { ref start, ref step, ref count } => {
+            if start.len() != step.len() || start.len() != count.len() || start.len() > rank {
+                return Err(shape_error(ErrorKind::IncompatibleShape))
+            }
+            let start: Vec<_> = (0..rank).map(|d| start.get(d).cloned().unwrap_or(0)).collect();
+            let step: Vec<_> = (0..rank).map(|d| step.get(d).cloned().unwrap_or(1)).collect();
+            let count: Vec<_> = (0..rank).map(|d| count.get(d).cloned().unwrap_or(shape[d])).collect();
+            // index of the last selected entry, which overflows for selections far out of bounds
+            let last = |d: usize| (count[d] - 1).checked_mul(step[d]).and_then(|i| i.checked_add(start[d]));
+            if (0..rank).any(|d| count[d] > 0 && last(d).filter(|&i| i < shape[d]).is_none()) {
+                return Err(shape_error(ErrorKind::OutOfBounds))
+            }
+            if count.contains(&0) {
+                return Ok((count, runs))
+            }
+
+            // the innermost dimensions are read in runs as long as they are contiguous
+            let mut inner = rank;
+            let mut length = 1;
+            while inner > 0 {
+                let d = inner - 1;
+                if count[d] > 1 && step[d] != 1 {
+                    break
+                }
+                length *= count[d];
+                inner -= 1;
+                if count[d] != shape[d] {
+                    break
+                }
+            }
+
+            let base: usize = (inner..rank).map(|d| start[d] * strides[d]).sum();
+            let mut index = vec![0; inner];
+            loop {
+                let offset: usize = (0..inner).map(|d| (start[d] + index[d] * step[d]) * strides[d]).sum();
+                push(&mut runs, base + offset, length);
+                if !next(&mut index, &count[..inner]) {
+                    break
+                }
+            }
+            Ok((count, runs))
+        }
+        Selection::Rows(ref rows) => {
+            if rank == 0 {
+                return Err(shape_error(ErrorKind::IncompatibleShape))
+            }
+            for &row in rows {
+                if row >= shape[0] {
+                    return Err(shape_error(ErrorKind::OutOfBounds))
+                }
+                push(&mut runs, row * strides[0], strides[0]);
+            }
+            let mut selected = shape.to_vec();
+            selected[0] = rows.len();
+            Ok((selected, runs))
+        }
+        Selection::Points(ref points) => {
+            for point in points {
+                if point.len() != rank {
+                    return Err(shape_error(ErrorKind::IncompatibleShape))
+                }
+                if point.iter().zip(shape).any(|(&i, &n)| i >= n) {
+                    return Err(shape_error(ErrorKind::OutOfBounds))
+                }
+                push(&mut runs, point.iter().zip(&strides).map(|(i, s)| i * s).sum(), 1);
+            }
+            Ok((vec![points.len()], runs))
+        }
+    }
+}
+
+/// Number of elements between consecutive entries in each dimension.
+///
+/// Fails if the number of elements of the dataset overflows, so that offsets of elements within
+/// the dataset do not.
+fn strides(shape: &[usize]) -> Result<Vec<usize>> {
+    let mut strides = vec![1usize; shape.len()];
+    for d in (1..shape.len()).rev() {
+        strides[d - 1] = strides[d].checked_mul(shape[d])
+            .ok_or_else(|| Error::Format("dataset too large".to_owned()))?;
+    }
+    if let Some(&n) = shape.first() {
+        strides[0].checked_mul(n).ok_or_else(|| Error::Format("dataset too large".to_owned()))?;
+    }
+    Ok(strides)
+}
+
+/// Append a run, merging it with the previous one if they are adjacent.
+fn push(runs: &mut Vec<Run>, offset: usize, length: usize) {
+    if length == 0 {
+        return
+    }
+    if let Some(last) = runs.last_mut() {
+        if last.0 + last.1 == offset {
+            last.1 += length;
+            return
+        }
+    }
+    runs.push((offset, length));
+}
+
+/// Advance a row-major index; false after the last one.
+fn next(index: &mut [usize], count: &[usize]) -> bool {
+    for d in (0..index.len()).rev() {
+        index[d] += 1;
+        if index[d] < count[d] {
+            return true
+        }
+        index[d] = 0;
+    }
+    false
+}
+
+fn shape_error(kind: ErrorKind) -> Error {
+    Error::NdError(ShapeError::from_kind(kind))
+}
@@ -0,0 +1,425 @@
+//! Where and how the raw data of datasets is stored
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use super::io::{btree, Bytes, Reader, UNDEFINED};
+use super::selection::Run;
+use super::{Error, Result};
+
+const DEFLATE: u16 = 1;
+const SHUFFLE: u16 = 2;
+const FLETCHER32: u16 = 3;
+
+/// Storage layout of a dataset.
+pub enum Layout {
+    /// The data is stored in the object header
+    Compact(Vec<u8>),
+
+    /// The data is stored in one block; the address is undefined if it has not been written
+    Contiguous {
+        address: u64,
+    },
+
+    /// The data is split into chunks of equal shape, which are indexed by a B-tree
+    Chunked {
+        address: u64,
+        chunk: Vec<usize>,
+    },
+}
+
+/// A filter that has been applied to the chunks of a dataset.
+pub struct Filter {
+    pub id: u16,
+    pub values: Vec<u32>,
+}
+
+/// Parse a data layout message.
+pub fn parse_layout(bytes: &mut Bytes) -> Result<Layout> {
+    let version = bytes.u8()?;
+    match version {
+        1 | 2 => {
+            let rank = bytes.u8()? as usize;
+            let class = bytes.u8()?;
+            bytes.skip(5)?;
+            match class {
+                0 => {
+                    bytes.skip(4 * rank)?;
+                    let size = bytes.u32()? as usize;
+                    Ok(Layout::Compact(bytes.take(size)?.to_owned()))
+                }
+                1 => Ok(Layout::Contiguous { address: bytes.address()? }),
+                _ => Err(Error::Unsupported(format!("layout class {} in layout message version {}", class, version))),
+            }
+        }
+        3 | 4 => {
+            let class = bytes.u8()?;
+            match class {
+                0 => {
+                    let size = bytes.u16()? as usize;
+                    Ok(Layout::Compact(bytes.take(size)?.to_owned()))
+                }
+                1 => Ok(Layout::Contiguous { address: bytes.address()? }),
+                2 if version == 3 => {
+                    // the last dimension is the size of an element
+                    let rank = bytes.u8()? as usize;
+                    let address = bytes.address()?;
+                    let mut chunk = Vec::with_capacity(rank);
+                    for _ in 0..rank {
+                        chunk.push(bytes.u32()? as usize);
+                    }
+                    chunk.pop();
+                    Ok(Layout::Chunked { address, chunk })
+                }
+                _ => Err(Error::Unsupported(format!("layout class {} in layout message version {}", class, version))),
+            }
+        }
+        _ => Err(Error::Unsupported(format!("layout message version {}", version))),
+    }
+}
+
+/// Parse a filter pipeline message.
+pub fn parse_filters(bytes: &mut Bytes) -> Result<Vec<Filter>> {
+    let version = bytes.u8()?;
+    let n = bytes.u8()? as usize;
+    if version == 1 {
+        bytes.skip(6)?;
+    } else if version != 2 {
+        return Err(Error::Unsupported(format!("filter pipeline message version {}", version)))
+    }
+
+    let mut filters = Vec::with_capacity(n);
+    for _ in 0..n {
+        let id = bytes.u16()?;
+        let name_length = if version == 1 || id >= 256 { bytes.u16()? as usize } else { 0 };
+        bytes.skip(2)?;  // flags
+        let n_values = bytes.u16()? as usize;
+        bytes.skip(name_length)?;
+
+        let mut values = Vec::with_capacity(n_values);
+        for _ in 0..n_values {
+            values.push(bytes.u32()?);
+        }
+        if version == 1 && n_values % 2 == 1 {
+            bytes.skip(4)?;
+        }
+
+        filters.push(Filter { id, values });
+    }
+    Ok(filters)
+}
+
+/// A chunk of a dataset.
+pub struct Chunk {
+    /// Position of the first element in the dataset
+    offset: Vec<usize>,
+
+    /// Size in bytes, after filtering
+    size: usize,
+
+    /// Filters that have not been applied to this chunk
+    filter_mask: u32,
+
+    address: u64,
+}
+
+/// The chunks of a dataset by the position of their first element.
+pub type ChunkIndex = HashMap<Vec<usize>, Chunk>;
+
+/// Read the index of the chunks of a dataset; it is empty unless the dataset is chunked.
+pub fn chunk_index(reader: &Reader, layout: &Layout, shape: &[usize]) -> Result<ChunkIndex> {
+    match *layout {
+        Layout::Chunked { address, .. } if address != UNDEFINED => {
+            Ok(chunks(reader, address, shape.len())?.into_iter()
+                .map(|entry| (entry.offset.clone(), entry))
+                .collect())
+        }
+        _ => Ok(ChunkIndex::new()),
+    }
+}
+
+/// Read all elements of a dataset in row-major order.
+pub fn read(reader: &Reader, layout: &Layout, filters: &[Filter], index: &ChunkIndex, shape: &[usize],
+            element_size: usize) -> Result<Vec<u8>> {
+    let size = data_size(shape, element_size)?;
+    match *layout {
+        Layout::Compact(ref data) => {
+            if data.len() < size {
+                return Err(Error::Format("compact data too small".to_owned()))
+            }
+            Ok(data[..size].to_owned())
+        }
+        Layout::Contiguous { address } => {
+            if address == UNDEFINED {
+                Ok(vec![0; size])
+            } else {
+                reader.read(address, size)
+            }
+        }
+        Layout::Chunked { address, ref chunk } => {
+            let mut data = vec![0; size];
+            if address == UNDEFINED {
+                return Ok(data)
+            }
+            if chunk.len() != shape.len() || shape.is_empty() {
+                return Err(Error::Format("chunk rank does not match dataset rank".to_owned()))
+            }
+
+            let chunk_size = data_size(chunk, element_size)?;
+            for entry in index.values() {
+                let raw = read_chunk(reader, entry, filters, chunk_size)?;
+                copy_chunk(&raw, &entry.offset, chunk, &mut data, shape, element_size);
+            }
+            Ok(data)
+        }
+    }
+}
+
+/// Read runs of consecutive elements of a dataset.
+///
+/// Only the parts of contiguous data and the chunks that contain selected elements are read. Each
+/// chunk is decoded once and dropped before the next one, so at most one decoded chunk is held in
+/// memory besides the result.
+pub fn read_runs(reader: &Reader, layout: &Layout, filters: &[Filter], index: &ChunkIndex, shape: &[usize],
+                 element_size: usize, runs: &[Run]) -> Result<Vec<u8>> {
+    // the runs lie within the dataset, so positions in bytes cannot overflow if its size does not
+    data_size(shape, element_size)?;
+    let size = runs.iter().map(|run| run.1).sum::<usize>().checked_mul(element_size)
+        .ok_or_else(|| Error::Format("selection too large".to_owned()))?;
+    let mut data = Vec::with_capacity(size);
+    match *layout {
+        Layout::Compact(ref compact) => {
+            for &(start, n) in runs {
+                let run = compact.get(start * element_size..(start + n) * element_size)
+                    .ok_or_else(|| Error::Format("compact data too small".to_owned()))?;
+                data.extend(run);
+            }
+        }
+        Layout::Contiguous { address } | Layout::Chunked { address, .. } if address == UNDEFINED => {
+            data.resize(size, 0);
+        }
+        Layout::Contiguous { address } => {
+            for &(start, n) in runs {
+                let run = address.checked_add((start * element_size) as u64)
+                    .ok_or_else(|| Error::Format("address out of range".to_owned()))?;
+                data.extend(reader.read(run, n * element_size)?);
+            }
+        }
+        Layout::Chunked { ref chunk, .. } => {
+            if chunk.len() != shape.len() || shape.is_empty() {
+                return Err(Error::Format("chunk rank does not match dataset rank".to_owned()))
+            }
+            let chunk_size = data_size(chunk, element_size)?;
+            let last = shape.len() - 1;
+
+            // split the runs into pieces that lie within a row of a single chunk: the origin of the
+            // chunk and the positions of the piece in the chunk and in the result
+            let mut pieces = Vec::new();
+            let mut dst = 0;
+            for &(start, n) in runs {
+                let mut position = start;
+                while position < start + n {
+                    let element = unravel(position, shape);
+                    let origin: Vec<_> = element.iter().zip(chunk).map(|(&i, &c)| i / c * c).collect();
+                    let piece = (start + n - position)
+                        .min(shape[last] - element[last])
+                        .min(origin[last] + chunk[last] - element[last]);
+                    let within: Vec<_> = element.iter().zip(&origin).map(|(i, o)| i - o).collect();
+                    pieces.push((origin, ravel(&within, chunk), dst, piece));
+                    position += piece;
+                    dst += piece;
+                }
+            }
+
+            // copy the pieces chunk by chunk; chunks that have not been written read as zeros
+            pieces.sort_by(|a, b| a.0.cmp(&b.0));
+            data.resize(size, 0);
+            let mut i = 0;
+            while i < pieces.len() {
+                let origin = &pieces[i].0;
+                let group = &pieces[i..i + pieces[i..].iter().take_while(|p| &p.0 == origin).count()];
+                if let Some(entry) = index.get(origin) {
+                    let raw = read_chunk(reader, entry, filters, chunk_size)?;
+                    for &(_, src, dst, n) in group {
+                        let (src, dst, n) = (src * element_size, dst * element_size, n * element_size);
+                        data[dst..dst + n].copy_from_slice(&raw[src..src + n]);
+                    }
+                }
+                i += group.len();
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// Read a chunk and undo its filters.
+fn read_chunk(reader: &Reader, entry: &Chunk, filters: &[Filter], chunk_size: usize) -> Result<Vec<u8>> {
+    let raw = unfilter(reader.read(entry.address, entry.size)?, filters, entry.filter_mask, chunk_size)?;
+    if raw.len() < chunk_size {
+        return Err(Error::Format("chunk too small".to_owned()))
+    }
+    Ok(raw)
+}
+
+/// Size in bytes of the elements of an array with given shape.
+fn data_size(shape: &[usize], element_size: usize) -> Result<usize> {
+    shape.iter()
+        .try_fold(element_size, |size, &n| size.checked_mul(n))
+        .ok_or_else(|| Error::Format("dataset too large".to_owned()))
+}
+
+/// Copy the part of a chunk that lies within the dataset into the dataset's data.
+fn copy_chunk(raw: &[u8], offset: &[usize], chunk: &[usize], data: &mut [u8], shape: &[usize], element_size: usize) {
+    let rank = shape.len();
+    if offset.iter().zip(shape).any(|(&o, &n)| o >= n) {
+        return
+    }
+
+    // copy row by row along the last dimension
+    let row = (chunk[rank - 1].min(shape[rank - 1] - offset[rank - 1])) * element_size;
+    let extent: Vec<_> = (0..rank).map(|d| chunk[d].min(shape[d] - offset[d])).collect();
+    let mut index = vec![0; rank];
+    loop {
+        let mut src = 0;
+        let mut dst = 0;
+        for d in 0..rank {
+            src = src * chunk[d] + index[d];
+            dst = dst * shape[d] + offset[d] + index[d];
+        }
+        let (src, dst) = (src * element_size, dst * element_size);
+        data[dst..dst + row].copy_from_slice(&raw[src..src + row]);
+
+        // advance all but the last dimension
+        let mut d = rank - 1;
+        loop {
+            if d == 0 {
+                return
+            }
+            d -= 1;
+            index[d] += 1;
+            if index[d] < extent[d] {
+                break
+            }
+            index[d] = 0;
+        }
+    }
+}
+
+/// Collect the chunks from the B-tree at given address.
+fn chunks(reader: &Reader, address: u64, rank: usize) -> Result<Vec<Chunk>> {
+    let mut chunks = Vec::new();
+    let key_size = 8 + 8 * (rank + 1);
+    btree(reader, address, 1, key_size, &mut |key, address| {
+        let mut key = reader.bytes(key);
+        let size = key.u32()? as usize;
+        let filter_mask = key.u32()?;
+        let mut offset = Vec::with_capacity(rank);
+        for _ in 0..rank {
+            offset.push(key.u64()? as usize);
+        }
+        chunks.push(Chunk { offset, size, filter_mask, address });
+        Ok(())
+    })?;
+    Ok(chunks)
+}
+
+/// Row-major index of the element at given position.
+fn unravel(mut position: usize, shape: &[usize]) -> Vec<usize> {
+    let mut index = vec![0; shape.len()];
+    for d in (0..shape.len()).rev() {
+        index[d] = position % shape[d];
+        position /= shape[d];
+    }
+    index
+}
+
+/// Position of the element at given row-major index.
+fn ravel(index: &[usize], shape: &[usize]) -> usize {
+    index.iter().zip(shape).fold(0, |i, (&j, &n)| i * n + j)
+}
+
+/// Undo the filters that have been applied to a chunk, in reverse order.
+///
+/// Decompressed data may not exceed `chunk_size` plus the checksums that are still attached, so
+/// that corrupted chunks cannot inflate to arbitrary size.
+fn unfilter(mut data: Vec<u8>, filters: &[Filter], mask: u32, chunk_size: usize) -> Result<Vec<u8>> {
+    let limit = chunk_size + 4 * filters.iter().filter(|f| f.id == FLETCHER32).count();
+    for (i, filter) in filters.iter().enumerate().rev() {
+        // the mask has room for the first 32 filters
+        if i < 32 && mask & (1 << i) != 0 {
+            continue
+        }
+        data = match filter.id {
+            DEFLATE => {
+                let mut output = Vec::with_capacity(limit.min(data.len() * 4));
+                ZlibDecoder::new(&data[..]).take(limit as u64 + 1)
+                    .read_to_end(&mut output).map_err(Error::IoError)?;
+                if output.len() > limit {
+                    return Err(Error::Format("decompressed chunk too large".to_owned()))
+                }
+                output
+            }
+            SHUFFLE => {
+                let size = filter.values.first().cloned().unwrap_or(1) as usize;
+                unshuffle(&data, size)
+            }
+            FLETCHER32 => {
+                if data.len() < 4 {
+                    return Err(Error::Format("chunk too small for checksum".to_owned()))
+                }
+                let n = data.len() - 4;
+                let stored = data[n..].iter().rev().fold(0, |x, &b| x << 8 | b as u32);
+                data.truncate(n);
+
+                // old versions of the library stored the checksum with swapped bytes
+                let checksum = fletcher32(&data);
+                let swapped = checksum.rotate_left(16);
+                let swapped = (swapped & 0x00ff_00ff) << 8 | (swapped & 0xff00_ff00) >> 8;
+                if stored != checksum && stored != swapped {
+                    return Err(Error::Format("chunk checksum mismatch".to_owned()))
+                }
+                data
+            }
+            id => return Err(Error::Unsupported(format!("filter {}", id))),
+        };
+    }
+    Ok(data)
+}
+
+/// Fletcher checksum of the 16-bit big endian words of `data`, as computed by the HDF5 library.
+///
+/// An odd trailing byte is treated as the high byte of a last word.
+pub fn fletcher32(data: &[u8]) -> u32 {
+    let (mut sum1, mut sum2) = (0u32, 0u32);
+    // the sums cannot overflow before they are reduced after 360 words
+    for block in data.chunks(720) {
+        for word in block.chunks(2) {
+            sum1 += (word[0] as u32) << 8 | word.get(1).cloned().unwrap_or(0) as u32;
+            sum2 += sum1;
+        }
+        sum1 = (sum1 & 0xffff) + (sum1 >> 16);
+        sum2 = (sum2 & 0xffff) + (sum2 >> 16);
+    }
+    sum1 = (sum1 & 0xffff) + (sum1 >> 16);
+    sum2 = (sum2 & 0xffff) + (sum2 >> 16);
+    sum2 << 16 | sum1
+}
+
+/// Undo the shuffle filter, which stores the first bytes of all elements, then the second bytes,
+/// and so on.
+fn unshuffle(data: &[u8], size: usize) -> Vec<u8> {
+    if size <= 1 {
+        return data.to_owned()
+    }
+
+    let n = data.len() / size;
+    let mut output = data.to_owned();
+    for i in 0..n {
+        for j in 0..size {
+            output[i * size + j] = data[j * n + i];
+        }
+    }
+    output
+}
@@ -0,0 +1,616 @@
+//! Minimal HDF5 writer to create test files
+//!
+//! Files have 8 byte addresses and lengths, a version 0 superblock, old style groups and version 1
+//! object headers, like the files written by HDF5 1.8 with default settings. Version 2 object
+//! headers with link messages can be written, too.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use utils::temp::TempDir;
+
+use super::storage::fletcher32;
+
+const UNDEFINED: u64 = !0;
+const SUPERBLOCK_SIZE: usize = 96;
+
+/// How the elements of a dataset are stored.
+pub enum Storage<'a> {
+    Contiguous,
+    Compact,
+
+    /// Chunks of given shape, with (filter id, client data) applied in order
+    Chunked(&'a [usize], &'a [(u16, &'a [u32])]),
+}
+
+/// A member of a group.
+pub enum Member<'a> {
+    Hard(u64),
+    Soft(&'a str),
+}
+
+pub struct Writer {
+    data: Vec<u8>,
+
+    /// Messages that are added to the next object header, e.g. attributes
+    pending: Vec<(u16, Vec<u8>)>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Writer {
+            data: vec![0; SUPERBLOCK_SIZE],
+            pending: Vec::new(),
+        }
+    }
+
+    /// Add a message to the next object header that is written.
+    pub fn attach(&mut self, message: (u16, Vec<u8>)) {
+        self.pending.push(message);
+    }
+
+    /// Append data at an address that is a multiple of 8.
+    pub fn alloc(&mut self, data: &[u8]) -> u64 {
+        let address = self.next_address();
+        self.data.resize(address as usize, 0);
+        self.data.extend(data);
+        address
+    }
+
+    /// The address at which the next data is appended.
+    pub fn next_address(&self) -> u64 {
+        (self.data.len() as u64 + 7) / 8 * 8
+    }
+
+    /// Version 1 object header; messages from `split` on go into a continuation block.
+    pub fn object_header(&mut self, messages: &[(u16, Vec<u8>)], split: Option<usize>) -> u64 {
+        let messages: Vec<_> = messages.iter().cloned().chain(self.pending.drain(..)).collect();
+        let split = split.unwrap_or(messages.len());
+        let mut first = encode_v1(&messages[..split]);
+        let mut count = split;
+
+        if split < messages.len() {
+            let rest = encode_v1(&messages[split..]);
+            let address = self.alloc(&rest);
+            let mut continuation = Vec::new();
+            put(&mut continuation, address, 8);
+            put(&mut continuation, rest.len() as u64, 8);
+            first.extend(encode_v1(&[(0x0010, continuation)]));
+            count = messages.len() + 1;
+        }
+
+        let mut header = vec![1, 0];
+        put(&mut header, count as u64, 2);
+        put(&mut header, 1, 4);
+        put(&mut header, first.len() as u64, 4);
+        put(&mut header, 0, 4);
+        header.extend(first);
+        self.alloc(&header)
+    }
+
+    /// Version 1 object header whose continuation message points back to its own messages, as
+    /// found in corrupted files.
+    pub fn object_header_loop(&mut self, messages: &[(u16, Vec<u8>)]) -> u64 {
+        let address = self.next_address();
+        let mut continuation = Vec::new();
+        put(&mut continuation, address + 16, 8);
+        put(&mut continuation, 0, 8);
+
+        let mut messages = messages.to_vec();
+        messages.push((0x0010, continuation));
+        let size = encode_v1(&messages).len();
+        let last = messages.len() - 1;
+        messages[last].1.truncate(8);
+        put(&mut messages[last].1, size as u64, 8);
+
+        let block = encode_v1(&messages);
+        let mut header = vec![1, 0];
+        put(&mut header, messages.len() as u64, 2);
+        put(&mut header, 1, 4);
+        put(&mut header, block.len() as u64, 4);
+        put(&mut header, 0, 4);
+        header.extend(block);
+        self.alloc(&header)
+    }
+
+    /// Version 2 object header without times, attribute phase change values or creation order.
+    pub fn object_header_v2(&mut self, messages: &[(u16, Vec<u8>)]) -> u64 {
+        let messages: Vec<_> = messages.iter().cloned().chain(self.pending.drain(..)).collect();
+        let mut body = Vec::new();
+        for &(kind, ref data) in &messages {
+            body.push(kind as u8);
+            put(&mut body, data.len() as u64, 2);
+            body.push(0);
+            body.extend(data);
+        }
+
+        let mut header = b"OHDR".to_vec();
+        header.extend(&[2, 0x02]);
+        put(&mut header, body.len() as u64, 4);
+        header.extend(body);
+        put(&mut header, 0, 4);  // checksum, which is not verified
+        self.alloc(&header)
+    }
+
+    /// Old style group with a local heap and a single symbol table node.
+    pub fn group(&mut self, members: &[(&str, Member)]) -> u64 {
+        let mut members: Vec<_> = members.iter().collect();
+        members.sort_by_key(|member| member.0);
+
+        let mut heap = vec![0; 8];
+        let mut entries = Vec::new();
+        let mut last_name = 0;
+        for &&(name, ref member) in &members {
+            last_name = heap.len() as u64;
+            put_string(&mut heap, name);
+            put(&mut entries, last_name, 8);
+            match *member {
+                Member::Hard(address) => {
+                    put(&mut entries, address, 8);
+                    put(&mut entries, 0, 8);
+                    entries.extend(&[0; 16]);
+                }
+                Member::Soft(target) => {
+                    put(&mut entries, UNDEFINED, 8);
+                    put(&mut entries, 2, 4);
+                    put(&mut entries, 0, 4);
+                    put(&mut entries, heap.len() as u64, 4);
+                    entries.extend(&[0; 12]);
+                    put_string(&mut heap, target);
+                }
+            }
+        }
+
+        let local_heap = self.local_heap(&heap);
+
+        let mut node = b"SNOD".to_vec();
+        node.extend(&[1, 0]);
+        put(&mut node, members.len() as u64, 2);
+        node.extend(entries);
+        let node = self.alloc(&node);
+
+        let mut tree = b"TREE".to_vec();
+        tree.extend(&[0, 0]);
+        put(&mut tree, 1, 2);
+        put(&mut tree, UNDEFINED, 8);
+        put(&mut tree, UNDEFINED, 8);
+        put(&mut tree, 0, 8);
+        put(&mut tree, node, 8);
+        put(&mut tree, last_name, 8);
+        let tree = self.alloc(&tree);
+
+        let mut symbol_table = Vec::new();
+        put(&mut symbol_table, tree, 8);
+        put(&mut symbol_table, local_heap, 8);
+        self.object_header(&[(0x0011, symbol_table)], None)
+    }
+
+    /// Old style group whose B-tree node refers to itself, as found in corrupted files.
+    pub fn group_loop(&mut self) -> u64 {
+        let local_heap = self.local_heap(&[0; 8]);
+
+        let tree = self.next_address();
+        let mut node = b"TREE".to_vec();
+        node.extend(&[0, 1]);
+        put(&mut node, 1, 2);
+        put(&mut node, UNDEFINED, 8);
+        put(&mut node, UNDEFINED, 8);
+        put(&mut node, 0, 8);
+        put(&mut node, tree, 8);
+        put(&mut node, 0, 8);
+        self.alloc(&node);
+
+        let mut symbol_table = Vec::new();
+        put(&mut symbol_table, tree, 8);
+        put(&mut symbol_table, local_heap, 8);
+        self.object_header(&[(0x0011, symbol_table)], None)
+    }
+
+    fn local_heap(&mut self, heap: &[u8]) -> u64 {
+        let heap_data = self.alloc(heap);
+        let mut local_heap = b"HEAP".to_vec();
+        local_heap.extend(&[0; 4]);
+        put(&mut local_heap, heap.len() as u64, 8);
+        put(&mut local_heap, UNDEFINED, 8);
+        put(&mut local_heap, heap_data, 8);
+        self.alloc(&local_heap)
+    }
+
+    /// Dataset of given shape; `data` holds the raw elements in row-major order.
+    pub fn dataset(&mut self, shape: &[usize], datatype: Vec<u8>, data: &[u8], storage: Storage) -> u64 {
+        let element_size = datatype_size(&datatype);
+        let mut messages = vec![(0x0001, dataspace(shape)), (0x0003, datatype)];
+
+        let mut layout = vec![3];
+        match storage {
+            Storage::Contiguous => {
+                let address = self.alloc(data);
+                layout.push(1);
+                put(&mut layout, address, 8);
+                put(&mut layout, data.len() as u64, 8);
+            }
+            Storage::Compact => {
+                layout.push(0);
+                put(&mut layout, data.len() as u64, 2);
+                layout.extend(data);
+            }
+            Storage::Chunked(chunk, filters) => {
+                let tree = self.chunks(shape, chunk, filters, element_size, data);
+                layout.extend(&[2, shape.len() as u8 + 1]);
+                put(&mut layout, tree, 8);
+                for &n in chunk {
+                    put(&mut layout, n as u64, 4);
+                }
+                put(&mut layout, element_size as u64, 4);
+
+                if !filters.is_empty() {
+                    let mut pipeline = vec![1, filters.len() as u8, 0, 0, 0, 0, 0, 0];
+                    for &(id, values) in filters {
+                        put(&mut pipeline, id as u64, 2);
+                        put(&mut pipeline, 0, 2);
+                        put(&mut pipeline, 0, 2);
+                        put(&mut pipeline, values.len() as u64, 2);
+                        for &value in values {
+                            put(&mut pipeline, value as u64, 4);
+                        }
+                        if values.len() % 2 == 1 {
+                            put(&mut pipeline, 0, 4);
+                        }
+                    }
+                    messages.push((0x000B, pipeline));
+                }
+            }
+        }
+        messages.push((0x0008, layout));
+
+        self.object_header(&messages, None)
+    }
+
+    /// Write the chunks and a B-tree that indexes them.
+    fn chunks(&mut self, shape: &[usize], chunk: &[usize], filters: &[(u16, &[u32])], element_size: usize, data: &[u8]) -> u64 {
+        let rank = shape.len();
+        let grid: Vec<_> = (0..rank).map(|d| (shape[d] + chunk[d] - 1) / chunk[d]).collect();
+        let chunk_len: usize = chunk.iter().product();
+
+        let mut keys = Vec::new();
+        for g in 0..grid.iter().product() {
+            let position = unravel(g, &grid);
+            let offset: Vec<_> = (0..rank).map(|d| position[d] * chunk[d]).collect();
+
+            let mut raw = Vec::new();
+            for i in 0..chunk_len {
+                let index: Vec<_> = unravel(i, chunk).iter().zip(&offset).map(|(i, o)| i + o).collect();
+                if index.iter().zip(shape).all(|(i, n)| i < n) {
+                    let j = ravel(&index, shape) * element_size;
+                    raw.extend(&data[j..j + element_size]);
+                } else {
+                    raw.extend(vec![0; element_size]);
+                }
+            }
+
+            for &(id, values) in filters {
+                raw = filter(id, values, raw);
+            }
+
+            let address = self.alloc(&raw);
+            keys.push((raw.len(), offset, address));
+        }
+
+        let mut tree = b"TREE".to_vec();
+        tree.extend(&[1, 0]);
+        put(&mut tree, keys.len() as u64, 2);
+        put(&mut tree, UNDEFINED, 8);
+        put(&mut tree, UNDEFINED, 8);
+        for (size, offset, address) in keys {
+            put(&mut tree, size as u64, 4);
+            put(&mut tree, 0, 4);
+            for o in offset {
+                put(&mut tree, o as u64, 8);
+            }
+            put(&mut tree, 0, 8);
+            put(&mut tree, address, 8);
+        }
+        put(&mut tree, 0, 8);
+        for &n in shape {
+            put(&mut tree, n as u64, 8);
+        }
+        put(&mut tree, 0, 8);
+        self.alloc(&tree)
+    }
+
+    /// Global heap collection with given objects, which get indices starting at 1.
+    pub fn global_heap(&mut self, objects: &[&[u8]]) -> u64 {
+        let mut body = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            put(&mut body, i as u64 + 1, 2);
+            put(&mut body, 1, 2);
+            put(&mut body, 0, 4);
+            put(&mut body, object.len() as u64, 8);
+            body.extend(*object);
+            while body.len() % 8 != 0 {
+                body.push(0);
+            }
+        }
+        body.extend(&[0; 16]);
+
+        let mut collection = b"GCOL".to_vec();
+        collection.extend(&[1, 0, 0, 0]);
+        put(&mut collection, body.len() as u64 + 16, 8);
+        collection.extend(body);
+        self.alloc(&collection)
+    }
+
+    /// The file contents with a version 0 superblock.
+    pub fn finish(mut self, root: u64) -> Vec<u8> {
+        let end = self.data.len() as u64;
+        let mut superblock = b"\x89HDF\r\n\x1a\n".to_vec();
+        superblock.extend(&[0, 0, 0, 0, 0, 8, 8, 0]);
+        put(&mut superblock, 4, 2);
+        put(&mut superblock, 16, 2);
+        put(&mut superblock, 0, 4);
+        put(&mut superblock, 0, 8);
+        put(&mut superblock, UNDEFINED, 8);
+        put(&mut superblock, end, 8);
+        put(&mut superblock, UNDEFINED, 8);
+        put(&mut superblock, 0, 8);
+        put(&mut superblock, root, 8);
+        put(&mut superblock, 0, 8);
+        superblock.extend(&[0; 16]);
+        self.data[..SUPERBLOCK_SIZE].copy_from_slice(&superblock);
+        self.data
+    }
+
+    /// The file contents with a version 2 superblock.
+    pub fn finish_v2(mut self, root: u64) -> Vec<u8> {
+        let end = self.data.len() as u64;
+        let mut superblock = b"\x89HDF\r\n\x1a\n".to_vec();
+        superblock.extend(&[2, 8, 8, 0]);
+        put(&mut superblock, 0, 8);
+        put(&mut superblock, UNDEFINED, 8);
+        put(&mut superblock, end, 8);
+        put(&mut superblock, root, 8);
+        put(&mut superblock, 0, 4);
+        self.data[..superblock.len()].copy_from_slice(&superblock);
+        self.data
+    }
+}
+
+/// A file in a directory of its own, which is removed when this is dropped.
+pub struct TempFile {
+    _dir: TempDir,
+    path: PathBuf,
+}
+
+impl AsRef<Path> for TempFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Save file contents in a new temporary directory.
+pub fn save(name: &str, data: &[u8]) -> TempFile {
+    let dir = TempDir::new("hdf5");
+    let path = dir.join(format!("{}.hdf5", name));
+    fs::write(&path, data).unwrap();
+    TempFile { _dir: dir, path }
+}
+
+/// Datatype message of an integer.
+pub fn integer(size: usize, signed: bool, big_endian: bool) -> Vec<u8> {
+    let flags = if signed { 0x08 } else { 0 } | if big_endian { 0x01 } else { 0 };
+    let mut message = vec![0x10, flags, 0, 0];
+    put(&mut message, size as u64, 4);
+    put(&mut message, 0, 2);
+    put(&mut message, 8 * size as u64, 2);
+    message
+}
+
+/// Datatype message of an IEEE floating point number.
+pub fn float(size: usize, big_endian: bool) -> Vec<u8> {
+    let flags = 0x20 | if big_endian { 0x01 } else { 0 };
+    let (sign, exponent, mantissa, bias) = if size == 4 { (31, 8, 23, 127) } else { (63, 11, 52, 1023) };
+    let mut message = vec![0x11, flags, sign, 0];
+    put(&mut message, size as u64, 4);
+    put(&mut message, 0, 2);
+    put(&mut message, 8 * size as u64, 2);
+    message.extend(&[mantissa, exponent, 0, mantissa]);
+    put(&mut message, bias, 4);
+    message
+}
+
+/// Datatype message of a fixed-length, null-terminated string.
+pub fn fixed_string(size: usize) -> Vec<u8> {
+    let mut message = vec![0x13, 0, 0, 0];
+    put(&mut message, size as u64, 4);
+    message
+}
+
+/// Datatype message of a variable-length string.
+pub fn var_string() -> Vec<u8> {
+    let mut message = vec![0x19, 0x01, 0, 0];
+    put(&mut message, 16, 4);
+    message.extend(integer(1, false, false));
+    message
+}
+
+/// Datatype message of an enum with given integer base type.
+pub fn enumeration(base: Vec<u8>, members: &[(&str, u64)]) -> Vec<u8> {
+    let size = datatype_size(&base);
+    let big_endian = base[1] & 0x01 != 0;
+    let mut message = vec![0x18];
+    put(&mut message, members.len() as u64, 3);
+    put(&mut message, size as u64, 4);
+    message.extend(base);
+    for &(name, _) in members {
+        put_name(&mut message, name);
+    }
+    for &(_, value) in members {
+        let start = message.len();
+        put(&mut message, value, size);
+        if big_endian {
+            message[start..].reverse();
+        }
+    }
+    message
+}
+
+/// Datatype message of a compound type of given version, with (name, offset, datatype) fields.
+pub fn compound(version: u8, size: usize, fields: &[(&str, usize, Vec<u8>)]) -> Vec<u8> {
+    let mut message = vec![version << 4 | 6];
+    put(&mut message, fields.len() as u64, 3);
+    put(&mut message, size as u64, 4);
+    for &(name, offset, ref datatype) in fields {
+        if version < 3 {
+            put_name(&mut message, name);
+            put(&mut message, offset as u64, 4);
+        } else {
+            message.extend(name.as_bytes());
+            message.push(0);
+            put(&mut message, offset as u64, if size < 256 { 1 } else { 2 });
+        }
+        if version == 1 {
+            message.extend(&[0; 28]);
+        }
+        message.extend(datatype);
+    }
+    message
+}
+
+/// Reference to an object in the global heap, as stored in variable-length elements.
+pub fn heap_reference(length: usize, collection: u64, index: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    put(&mut data, length as u64, 4);
+    put(&mut data, collection, 8);
+    put(&mut data, index as u64, 4);
+    data
+}
+
+/// Version 1 attribute message.
+pub fn attribute(name: &str, shape: &[usize], datatype: Vec<u8>, data: &[u8]) -> (u16, Vec<u8>) {
+    let space = dataspace(shape);
+    let mut message = vec![1, 0];
+    put(&mut message, name.len() as u64 + 1, 2);
+    put(&mut message, datatype.len() as u64, 2);
+    put(&mut message, space.len() as u64, 2);
+    put_string(&mut message, name);
+    for part in &[datatype, space] {
+        message.extend(part);
+        while message.len() % 8 != 0 {
+            message.push(0);
+        }
+    }
+    message.extend(data);
+    (0x000C, message)
+}
+
+/// Version 1 dataspace message.
+pub fn dataspace(shape: &[usize]) -> Vec<u8> {
+    let mut message = vec![1, shape.len() as u8, 0, 0, 0, 0, 0, 0];
+    for &n in shape {
+        put(&mut message, n as u64, 8);
+    }
+    message
+}
+
+/// Link message of a hard link.
+pub fn link(name: &str, address: u64) -> Vec<u8> {
+    let mut message = vec![1, 0, name.len() as u8];
+    message.extend(name.as_bytes());
+    put(&mut message, address, 8);
+    message
+}
+
+/// Little endian bytes of a slice of values.
+pub fn bytes<T: Copy, F: Fn(T) -> u64>(values: &[T], size: usize, f: F) -> Vec<u8> {
+    let mut data = Vec::new();
+    for &x in values {
+        put(&mut data, f(x), size);
+    }
+    data
+}
+
+fn filter(id: u16, values: &[u32], raw: Vec<u8>) -> Vec<u8> {
+    match id {
+        1 => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(values[0]));
+            encoder.write_all(&raw).unwrap();
+            encoder.finish().unwrap()
+        }
+        2 => {
+            let size = values[0] as usize;
+            let n = raw.len() / size;
+            let mut output = raw.clone();
+            for i in 0..n {
+                for j in 0..size {
+                    output[j * n + i] = raw[i * size + j];
+                }
+            }
+            output
+        }
+        3 => {
+            let mut output = raw;
+            let checksum = fletcher32(&output) as u64;
+            put(&mut output, checksum, 4);
+            output
+        }
+        _ => panic!("unknown filter"),
+    }
+}
+
+fn encode_v1(messages: &[(u16, Vec<u8>)]) -> Vec<u8> {
+    let mut block = Vec::new();
+    for &(kind, ref data) in messages {
+        let size = (data.len() + 7) / 8 * 8;
+        put(&mut block, kind as u64, 2);
+        put(&mut block, size as u64, 2);
+        put(&mut block, 0, 4);
+        block.extend(data);
+        block.extend(vec![0; size - data.len()]);
+    }
+    block
+}
+
+fn datatype_size(message: &[u8]) -> usize {
+    message[4] as usize | (message[5] as usize) << 8
+}
+
+fn put(data: &mut Vec<u8>, value: u64, size: usize) {
+    for i in 0..size {
+        data.push((value >> (8 * i)) as u8);
+    }
+}
+
+fn put_string(data: &mut Vec<u8>, s: &str) {
+    data.extend(s.as_bytes());
+    data.push(0);
+    while data.len() % 8 != 0 {
+        data.push(0);
+    }
+}
+
+/// Null-terminated string padded to a multiple of 8 bytes, counted from its start.
+fn put_name(data: &mut Vec<u8>, s: &str) {
+    let start = data.len();
+    data.extend(s.as_bytes());
+    data.push(0);
+    while (data.len() - start) % 8 != 0 {
+        data.push(0);
+    }
+}
+
+fn unravel(mut i: usize, shape: &[usize]) -> Vec<usize> {
+    let mut index = vec![0; shape.len()];
+    for d in (0..shape.len()).rev() {
+        index[d] = i % shape[d];
+        i /= shape[d];
+    }
+    index
+}
+
+fn ravel(index: &[usize], shape: &[usize]) -> usize {
+    index.iter().zip(shape).fold(0, |i, (&j, &n)| i * n + j)
+}
@@ -1,4 +1,10 @@
+#[macro_use]
 pub mod downloader;
 pub mod error;
 pub mod hdf5;
 pub mod lzw;
+pub mod parse;
+pub mod random;
+
+#[cfg(test)]
+pub mod temp;
@@ -0,0 +1,87 @@
+//! Helpers for parsing delimited text files
+
+use std::path::Path;
+use std::str::FromStr;
+
+use utils::error::{Error, ParseError};
+
+/// Fields of a single line, which keeps track of the position for error reporting.
+pub struct Fields<'a, I> {
+    file: &'a Path,
+    line: usize,
+    column: usize,
+    fields: I,
+}
+
+impl<'a, 'b, I: Iterator<Item=&'b str>> Fields<'a, I> {
+    /// Fields of line number `line` (counting from 1) in `file`.
+    pub fn new(file: &'a Path, line: usize, fields: I) -> Self {
+        Fields {
+            file,
+            line,
+            column: 0,
+            fields,
+        }
+    }
+
+    /// The next field as string.
+    pub fn next_str(&mut self, expected: &'static str) -> Result<&'b str, Error> {
+        self.column += 1;
+        match self.fields.next() {
+            Some(token) => Ok(token),
+            None => Err(self.error("", expected)),
+        }
+    }
+
+    /// Parse the next field.
+    pub fn next<T: FromStr>(&mut self, expected: &'static str) -> Result<T, Error> {
+        let token = self.next_str(expected)?;
+        token.parse().map_err(|_| self.error(token, expected))
+    }
+
+    /// All remaining fields.
+    ///
+    /// Errors reported afterwards refer to the first of them.
+    pub fn rest(&mut self) -> Vec<&'b str> {
+        self.column += 1;
+        self.fields.by_ref().collect()
+    }
+
+    /// Error at the current field.
+    pub fn error(&self, token: &str, expected: &'static str) -> Error {
+        Error::Parse(ParseError {
+            file: self.file.to_owned(),
+            line: self.line,
+            column: self.column,
+            token: token.to_owned(),
+            expected,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let file = Path::new("test.data");
+        let mut fields = Fields::new(file, 7, "1.5,x".split(","));
+        assert_eq!(fields.next::<f32>("number").unwrap(), 1.5);
+
+        match fields.next::<f32>("number") {
+            Err(Error::Parse(e)) => {
+                assert_eq!((e.line, e.column), (7, 2));
+                assert_eq!(e.token, "x");
+                assert_eq!(e.file, file);
+            }
+            _ => panic!("expected parse error"),
+        }
+
+        match fields.next_str("class") {
+            Err(Error::Parse(e)) => assert_eq!((e.column, e.token.as_str(), e.expected), (3, "", "class")),
+            _ => panic!("expected parse error"),
+        }
+    }
+}
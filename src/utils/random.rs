@@ -0,0 +1,73 @@
+//! Simplistic seeded random number generation
+//!
+//! This is deliberately implemented here instead of depending on an external crate: the sequence
+//! of numbers generated for a given seed must never change, so that shuffles and splits are
+//! reproducible across platforms, machines and versions of this crate.
+
+/// A SplitMix64 pseudo random number generator.
+///
+/// Not suitable for cryptography, but fast and good enough for shuffling data.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            state: seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed integer in the range `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        let n = n as u64;
+        // reject the incomplete last interval to avoid modulo bias
+        let zone = ::std::u64::MAX - ::std::u64::MAX % n;
+        loop {
+            let r = self.next_u64();
+            if r < zone {
+                return (r % n) as usize
+            }
+        }
+    }
+
+    /// Shuffle a slice in place (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = self.below(i + 1);
+            values.swap(i, j);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        // reference values of the SplitMix64 algorithm
+        let mut rng = Rng::new(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+
+        let mut a: Vec<_> = (0..100).collect();
+        let mut b: Vec<_> = (0..100).collect();
+        Rng::new(42).shuffle(&mut a);
+        Rng::new(42).shuffle(&mut b);
+        assert_eq!(a, b);
+
+        b.sort();
+        assert_eq!(b, (0..100).collect::<Vec<_>>());
+    }
+}